@@ -5,9 +5,25 @@ use std::error::Error;
 use std::fs;
 use std::path::Path;
 
+const PORTFOLIO_URL: &str = "https://tpbeztjisfdyqmwrugpt.supabase.co/functions/v1/portfolio";
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
     dotenv().ok();
+    println!("cargo:rerun-if-env-changed=SUPABASE_TOKEN");
+    println!("cargo:rerun-if-env-changed=PORTFOLIO_URL");
+
+    // forward the endpoint and the (public) anon key so the runtime loader can use them
+    let url = env::var("PORTFOLIO_URL").unwrap_or(PORTFOLIO_URL.to_string());
+    println!("cargo:rustc-env=PORTFOLIO_URL={}", url);
+    if let Ok(token) = env::var("SUPABASE_TOKEN") {
+        println!("cargo:rustc-env=SUPABASE_TOKEN={}", token);
+    }
+
+    // the data file is only needed when it is compiled into the binary
+    if env::var("CARGO_FEATURE_EMBED_DATA").is_err() {
+        return Ok(());
+    }
 
     let dest_path = "./assets/data.json";
     let path = Path::new(dest_path);
@@ -24,11 +40,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
     );
 
     let client = reqwest::Client::new();
-    let res = client
-        .get("https://tpbeztjisfdyqmwrugpt.supabase.co/functions/v1/portfolio")
-        .headers(headers)
-        .send()
-        .await?;
+    let res = client.get(url).headers(headers).send().await?;
 
     let body = res.text().await?;
 
@@ -1,13 +1,23 @@
 use crate::modals::*;
+use crate::settings::Settings;
 use egui::scroll_area::ScrollBarVisibility;
 use egui::{Context, RichText};
 use sha2::Digest;
 
+const LEDGER_KEY: &str = "ledger";
+const SETTINGS_KEY: &str = "settings";
+const REBALANCE_KEY: &str = "rebalance";
+/// Height of the rows in the desktop Daily window.
+const DAILY_HEIGHT: f32 = 480.0;
+const COLUMNS_KEY: &str = "columns";
+
 #[derive(PartialEq, serde::Deserialize, serde::Serialize)]
 enum Tab {
     Holdings,
     Daily,
     History,
+    Balance,
+    Ledger,
 }
 
 /// We derive Deserialize/Serialize so we can persist app state on shutdown.
@@ -15,88 +25,505 @@ enum Tab {
 enum AppState {
     BeforeLogin = 0,
     LoggedIn = 1,
+    Loading = 2,
+    Failed = 3,
 }
 
 /// We derive Deserialize/Serialize. so we can persist app state on shutdown.
 #[derive(serde::Deserialize, serde::Serialize)]
 #[serde(default)] // if we add new fields, give them default values when deserializing old state
 pub struct WrapApp {
+    summary: Summary,
     pie_chart: PieChart,
     daily_table: DailyTable,
     history_chart: PeriodChart,
+    drawdown_chart: DrawdownChart,
+    balance_chart: LineChart,
+    performance: Performance,
+    risk_panel: RiskPanel,
+    benchmark_chart: BenchmarkChart,
+    candle_chart: CandleChart,
+    ledger_view: LedgerView,
+    lot_table: LotTable,
+    rebalance: RebalanceView,
 
     mobile_tab: Tab,
 
     app_state: AppState,
     input_password: String,
     stored_hash: String,
+
+    /// Ledger saved by an earlier session, replayed only once the password has been checked.
+    #[serde(skip)]
+    stored_ledger: Option<Ledger>,
+    /// Rebalance targets and cash saved by an earlier session, kept from guests like the ledger.
+    #[serde(skip)]
+    stored_rebalance: Option<RebalancePlan>,
+    /// Whether the password was entered, guests never see or overwrite the stored ledger.
+    #[serde(skip)]
+    authenticated: bool,
+    #[serde(skip)]
+    settings: Settings,
+    /// Whether the fonts were checked for the glyphs of the korean units.
+    #[serde(skip)]
+    fonts_checked: bool,
+    #[serde(skip)]
+    fx: FxRates,
+    /// Whether some converted amount is dated outside the rate series and got the nearest rate.
+    #[serde(skip)]
+    fx_clamped: bool,
+    #[serde(skip)]
+    benchmarks: Vec<Benchmark>,
+    #[serde(skip)]
+    classifications: Vec<Classification>,
+    /// Pie group of every holding, so a symbol picked elsewhere lights up its group.
+    #[serde(skip)]
+    groups: Vec<(String, String)>,
+    /// Daily table selection already passed on to the other widgets.
+    #[serde(skip)]
+    last_selected: Option<String>,
+    #[serde(skip)]
+    pf_data: PortfolioRawData,
+    #[serde(skip)]
+    loader: Option<DataLoader>,
+    #[serde(skip)]
+    load_error: Option<DataError>,
 }
 
 impl Default for WrapApp {
     fn default() -> Self {
-        let pf_data = PortfolioRawData::default();
-        let (pie_chart, daily_table, history_chart) = WrapApp::initialize_data(pf_data);
-        Self {
-            pie_chart,
-            daily_table,
-            history_chart,
+        let (pf_data, load_error) = match PortfolioRawData::dummy() {
+            Ok(pf_data) => (pf_data, None),
+            Err(err) => (PortfolioRawData::default(), Some(err)),
+        };
+        let mut app = Self {
+            summary: Summary::default(),
+            pie_chart: PieChart::default(),
+            daily_table: DailyTable::default(),
+            history_chart: PeriodChart::default(),
+            drawdown_chart: DrawdownChart::default(),
+            balance_chart: LineChart::default(),
+            performance: Performance::default(),
+            risk_panel: RiskPanel::default(),
+            benchmark_chart: BenchmarkChart::default(),
+            candle_chart: CandleChart::default(),
+            ledger_view: LedgerView::default(),
+            lot_table: LotTable::default(),
+            rebalance: RebalanceView::default(),
             mobile_tab: Tab::Holdings,
-            app_state: AppState::BeforeLogin,
+            app_state: match load_error {
+                Some(_) => AppState::Failed,
+                None => AppState::BeforeLogin,
+            },
             input_password: "".to_string(),
             stored_hash: "4ecdc4ec6c0e98bea7165bcb88f79d3a0a95461874705be912fa1d22abaa67ea"
                 .to_string(),
-        }
+            stored_ledger: None,
+            stored_rebalance: None,
+            authenticated: false,
+            settings: Settings::default(),
+            fonts_checked: false,
+            fx: FxRates::bundled().unwrap_or_else(|err| {
+                log::error!("failed to read usd-krw rates: {}", err);
+                FxRates::default()
+            }),
+            fx_clamped: false,
+            benchmarks: Benchmark::bundled().unwrap_or_else(|err| {
+                log::error!("failed to read benchmarks: {}", err);
+                vec![]
+            }),
+            classifications: Classification::bundled().unwrap_or_else(|err| {
+                log::error!("failed to read classifications: {}", err);
+                vec![]
+            }),
+            groups: vec![],
+            last_selected: None,
+            pf_data: PortfolioRawData::default(),
+            loader: None,
+            load_error,
+        };
+        app.initialize_data(pf_data);
+        app
     }
 }
 
 impl WrapApp {
     /// Called once before the first frame.
-    pub fn new(_cc: &eframe::CreationContext<'_>) -> Self {
-        Default::default()
+    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
+        // the monospace font carries glyphs like ₩ that the proportional one lacks
+        let mut fonts = egui::FontDefinitions::default();
+        fonts
+            .families
+            .entry(egui::FontFamily::Proportional)
+            .or_default()
+            .push("Hack".to_owned());
+        cc.egui_ctx.set_fonts(fonts);
+
+        let mut app: Self = Default::default();
+        if let Some(storage) = cc.storage {
+            app.stored_ledger = eframe::get_value::<Ledger>(storage, LEDGER_KEY);
+            if let Some(settings) = eframe::get_value::<Settings>(storage, SETTINGS_KEY) {
+                app.settings = settings;
+            }
+            app.stored_rebalance = eframe::get_value::<RebalancePlan>(storage, REBALANCE_KEY);
+            if let Some(layout) = eframe::get_value::<TableLayout>(storage, COLUMNS_KEY) {
+                app.daily_table = DailyTable::new(
+                    "daily",
+                    &[],
+                    layout,
+                    app.settings.currency,
+                    app.settings.colors,
+                    app.settings.format,
+                );
+            }
+            app.build_views();
+        }
+        app
+    }
+
+    fn load(&mut self, ctx: &Context) {
+        self.loader = Some(DataLoader::fetch(ctx));
+        self.load_error = None;
+        self.app_state = AppState::Loading;
     }
 
-    fn load(&mut self) {
-        let pf_data = PortfolioRawData::new();
-        let (pie_chart, daily_table, history_chart) = WrapApp::initialize_data(pf_data);
-        self.pie_chart = pie_chart;
-        self.daily_table = daily_table;
-        self.history_chart = history_chart;
+    fn apply(&mut self, pf_data: PortfolioRawData) {
+        if let Some(ledger) = self.stored_ledger.take() {
+            self.ledger_view = LedgerView::new("ledger", ledger);
+        }
+        if let Some(saved) = self.stored_rebalance.take() {
+            self.rebalance = RebalanceView::new(
+                "rebalance",
+                saved,
+                vec![],
+                self.settings.currency,
+                1.0,
+                self.settings.colors,
+                self.settings.format,
+            );
+        }
+        self.initialize_data(pf_data);
+        self.app_state = AppState::LoggedIn;
     }
 
-    fn initialize_data(pf_data: PortfolioRawData) -> (PieChart, DailyTable, PeriodChart) {
-        let pie_chart_data: Vec<(f64, String)> = pf_data
-            .holdings
+    fn fail(&mut self, err: DataError) {
+        self.load_error = Some(err);
+        self.app_state = AppState::Failed;
+    }
+
+    fn initialize_data(&mut self, pf_data: PortfolioRawData) {
+        if self.ledger_view.ledger().is_empty() && !pf_data.transactions.is_empty() {
+            let ledger = Ledger::new(pf_data.transactions.clone());
+            self.ledger_view = LedgerView::new("ledger", ledger);
+        }
+        self.pf_data = pf_data;
+        self.build_views();
+    }
+
+    /// Rebuilds every widget from the loaded data, with holdings replayed from the ledger if any.
+    fn build_views(&mut self) {
+        let pf_data = &self.pf_data;
+        let ledger = self.ledger_view.ledger();
+        let method = self.settings.cost_basis;
+        let cash_flows = ledger.cash_flows();
+        let cost_history = if ledger.is_empty() {
+            vec![]
+        } else {
+            ledger.cost_history(method).unwrap_or_default()
+        };
+        let replayed = if ledger.is_empty() {
+            None
+        } else {
+            match ledger.holdings(&pf_data.holdings, method) {
+                Ok(replayed) => {
+                    self.ledger_view.set_replay_error(None);
+                    Some(replayed)
+                }
+                Err(err) => {
+                    self.ledger_view.set_replay_error(Some(err.to_string()));
+                    None
+                }
+            }
+        };
+        // only lots replayed from the ledger can be named by a sell
+        let ledger_positions = replayed.as_ref().map(|(_, positions)| positions.as_slice());
+        self.ledger_view
+            .set_lots(ledger_positions.unwrap_or_default());
+        let (holdings, positions) = replayed.unwrap_or_else(|| {
+            let positions = pf_data.holdings.iter().map(Position::from_stock).collect();
+            (pf_data.holdings.clone(), positions)
+        });
+
+        // current figures use the latest rate, dated series the rate of their own day
+        let currency = self.settings.currency;
+        let colors = self.settings.colors;
+        let format = self.settings.format;
+        self.ledger_view.set_format(format);
+        let fx = &self.fx;
+        let factor = fx.factor(currency, None);
+        self.fx_clamped = currency == Currency::Krw
+            && !fx.is_empty()
+            && (pf_data.history.iter().map(|r| r.date.as_str()))
+                .chain(pf_data.prices.iter().map(|p| p.date.as_str()))
+                .chain(pf_data.holdings.iter().map(|s| s.update_at.as_str()))
+                .any(|date| !fx.covers(date));
+        let mut holdings: Vec<_> = holdings.iter().map(|s| s.scaled(factor)).collect();
+        for stock in &mut holdings {
+            Classification::apply(&self.classifications, stock);
+        }
+        let positions: Vec<_> = positions.iter().map(|p| p.scaled(factor)).collect();
+        let realized = positions.iter().map(|p| p.realized_pnl).sum();
+
+        let lot_data: Vec<_> = positions
+            .into_iter()
+            .filter_map(|position| {
+                let stock = holdings.iter().find(|s| s.symbol == position.symbol)?;
+                Some((position, stock.close_price))
+            })
+            .collect();
+        self.lot_table = LotTable::new("lots", method.label(), &lot_data, currency, colors, format);
+
+        let total_cost = holdings.iter().map(|data| data.cost_basis()).sum();
+        let market_value = holdings.iter().map(|data| data.market_value()).sum();
+        self.summary = Summary::new(total_cost, market_value, realized, currency, colors, format);
+
+        let dimension = self.settings.allocation;
+        self.groups = holdings
+            .iter()
+            .map(|data| (data.symbol.to_string(), dimension.group_of(data)))
+            .collect();
+        let mut pie_chart_data: Vec<Slice> = vec![];
+        for (data, (_, group)) in holdings.iter().zip(&self.groups) {
+            let slice = match pie_chart_data.iter_mut().find(|s| &s.label == group) {
+                Some(slice) => slice,
+                None => {
+                    pie_chart_data.push(Slice {
+                        label: group.to_string(),
+                        value: 0.0,
+                        shares: 0,
+                        pnl: 0.0,
+                    });
+                    pie_chart_data.last_mut().unwrap()
+                }
+            };
+            slice.value += data.market_value();
+            slice.shares += data.owned;
+            slice.pnl += data.unrealized_pnl();
+        }
+
+        let pie_chart = PieChart::new("Percents", &pie_chart_data, currency, colors, format);
+        let previous = std::mem::replace(&mut self.pie_chart, pie_chart);
+        self.pie_chart.keep_grouping(&previous);
+
+        let rebalance_data = holdings
+            .iter()
+            .map(|data| (data.symbol.to_string(), data.owned, data.close_price))
+            .collect();
+        self.rebalance = RebalanceView::new(
+            "rebalance",
+            self.rebalance.saved(),
+            rebalance_data,
+            currency,
+            factor,
+            colors,
+            format,
+        );
+
+        let selected = self.daily_table.selected().map(|s| s.to_string());
+        let layout = self.daily_table.layout().clone();
+        self.daily_table = DailyTable::new("daily", &holdings, layout, currency, colors, format);
+        self.daily_table.select(selected.as_deref());
+        let group = self.group_of(selected.as_deref());
+        self.pie_chart.select(group.as_deref());
+
+        let candles = holdings
             .iter()
             .map(|data| {
-                (
-                    data.owned as f64 * data.close_price,
-                    data.symbol.to_string(),
-                )
+                let mut candles: Vec<_> = pf_data
+                    .price_history(&data.symbol)
+                    .iter()
+                    .map(|price| {
+                        let factor = fx.factor(currency, Some(&price.date));
+                        price.scaled(factor).candle()
+                    })
+                    .collect();
+                // the stored bars may already hold the session the holding was quoted in
+                let latest = data.candle();
+                if candles.last().map(|c| c.date < latest.date) != Some(false) {
+                    candles.push(latest);
+                }
+                (data.symbol.to_string(), candles)
             })
             .collect();
+        self.daily_table.set_history(&candles);
+        self.candle_chart = CandleChart::new("candle", candles, currency, colors, format);
 
-        let pie_chart = PieChart::new("Percents", &pie_chart_data);
-
-        let daily_table_data: Vec<(String, String, f64, f64)> = pf_data
-            .holdings
+        let symbol_history = holdings
             .iter()
             .map(|data| {
-                let change =
-                    (data.close_price - data.yesterday_price) / data.yesterday_price * 100.0;
+                let records = pf_data
+                    .price_history(&data.symbol)
+                    .iter()
+                    .map(|price| Record {
+                        date: price.date.clone(),
+                        balance: data.owned as f64 * price.close,
+                    })
+                    .collect();
+                (data.symbol.to_string(), records)
+            })
+            .collect::<Vec<(String, Vec<Record>)>>();
+        // a selected holding narrows the panels below down to its own value, with its
+        // current share count over the whole history and so without flows
+        let focus = selected.as_deref().and_then(|symbol| {
+            let stock = holdings.iter().find(|s| s.symbol == symbol)?;
+            let (_, records) = symbol_history.iter().find(|(s, _)| s == symbol)?;
+            (!records.is_empty()).then(|| (records.clone(), stock.cost_basis()))
+        });
+        self.history_chart = PeriodChart::new(
+            "period",
+            pf_data.history.clone(),
+            symbol_history,
+            currency,
+            fx.clone(),
+            colors,
+            format,
+        );
+        self.history_chart.set_symbol(selected.as_deref());
+
+        let in_currency = |records: &[Record]| -> Vec<Record> {
+            records
+                .iter()
+                .map(|record| Record {
+                    date: record.date.clone(),
+                    balance: record.balance * fx.factor(currency, Some(&record.date)),
+                })
+                .collect()
+        };
+        let (balances, flows, cost) = match focus {
+            Some((records, cost_basis)) => {
+                let cost = records
+                    .first()
+                    .map(|record| {
+                        vec![Record {
+                            date: record.date.clone(),
+                            balance: cost_basis,
+                        }]
+                    })
+                    .unwrap_or_default();
+                (in_currency(&records), vec![], cost)
+            }
+            None => {
+                // without a ledger the only known cost is today's, drawn flat over the history
+                let cost = if cost_history.is_empty() {
+                    let first = pf_data.history.iter().map(|record| &record.date).min();
+                    first
+                        .map(|date| {
+                            vec![Record {
+                                date: date.clone(),
+                                balance: total_cost,
+                            }]
+                        })
+                        .unwrap_or_default()
+                } else {
+                    in_currency(&cost_history)
+                };
                 (
-                    data.symbol.to_string(),
-                    data.company_name.to_string(),
-                    data.close_price,
-                    change,
+                    in_currency(&pf_data.history),
+                    in_currency(&cash_flows),
+                    cost,
                 )
-            })
+            }
+        };
+        self.balance_chart = LineChart::new("balance", balances.clone(), cost, currency, format);
+        self.performance = Performance::new(
+            "performance",
+            balances.clone(),
+            flows.clone(),
+            currency,
+            colors,
+            format,
+        );
+        self.performance.set_range(self.history_chart.range());
+        self.risk_panel = RiskPanel::new(
+            "risk",
+            balances.clone(),
+            flows.clone(),
+            self.settings.risk_free_rate,
+            colors,
+        );
+        self.risk_panel.set_range(self.history_chart.range());
+        self.drawdown_chart =
+            DrawdownChart::new("drawdown", balances.clone(), flows.clone(), colors);
+        self.drawdown_chart.set_range(self.history_chart.range());
+
+        let mut benchmarks = self.benchmarks.clone();
+        Benchmark::merge(
+            &mut benchmarks,
+            Benchmark::from_closes(&pf_data.benchmarks).unwrap_or_default(),
+        );
+        // each index is valued in the display currency from the one it is quoted in
+        let benchmarks = benchmarks
+            .iter()
+            .map(|benchmark| benchmark.converted(fx, currency))
             .collect();
+        self.benchmark_chart = BenchmarkChart::new(
+            "benchmark",
+            balances,
+            flows,
+            benchmarks,
+            self.settings.risk_free_rate,
+            colors,
+        );
+        self.benchmark_chart.set_range(self.history_chart.range());
+    }
 
-        let daily_table = DailyTable::new("daily", &daily_table_data);
+    /// Pie label of the group `symbol` belongs to.
+    fn group_of(&self, symbol: Option<&str>) -> Option<String> {
+        let symbol = symbol?;
+        self.groups
+            .iter()
+            .find(|(s, _)| s == symbol)
+            .map(|(_, group)| group.to_string())
+    }
+
+    /// Makes `symbol` the selected holding in every widget that follows the selection.
+    fn select(&mut self, symbol: Option<&str>) {
+        self.daily_table.select(symbol);
+        self.last_selected = symbol.map(|s| s.to_string());
+        // the balance based panels follow the selection, so they are rebuilt around it
+        self.build_views();
+    }
 
-        let history_chart = PeriodChart::new("period", pf_data.history);
+    /// Carries a selection made in the daily table over to the other widgets.
+    fn sync_selection(&mut self) {
+        if self.daily_table.selected() != self.last_selected.as_deref() {
+            let symbol = self.daily_table.selected().map(|s| s.to_string());
+            self.select(symbol.as_deref());
+        }
+    }
 
-        (pie_chart, daily_table, history_chart)
+    /// Allocation pie with the dimension it groups the holdings by.
+    fn show_allocation(&mut self, ui: &mut egui::Ui) {
+        let before = self.settings.allocation;
+        egui::ComboBox::from_label("Group by")
+            .selected_text(before.label())
+            .show_ui(ui, |ui| {
+                for dimension in Dimension::ALL {
+                    ui.selectable_value(
+                        &mut self.settings.allocation,
+                        dimension,
+                        dimension.label(),
+                    );
+                }
+            });
+        if self.settings.allocation != before {
+            self.build_views();
+        }
+        // a group picks no single holding, so only symbols travel to the other widgets
+        if self.pie_chart.show(ui) && self.settings.allocation == Dimension::Symbol {
+            let symbol = self.pie_chart.selected().map(|s| s.to_string());
+            self.select(symbol.as_deref());
+        }
     }
 
     fn mobile_view(&mut self, ui: &mut egui::Ui) {
@@ -104,15 +531,44 @@ impl WrapApp {
             .scroll_bar_visibility(ScrollBarVisibility::AlwaysHidden)
             .show(ui, |ui| match self.mobile_tab {
                 Tab::Holdings => {
-                    self.pie_chart.show(ui);
+                    self.summary.show(ui);
+                    self.show_allocation(ui);
+                    ui.separator();
+                    self.rebalance.show(ui);
                 }
                 Tab::Daily => {
+                    self.daily_table.set_scroll_height(None);
                     self.daily_table.show(ui);
+                    self.candle_chart.select(self.daily_table.selected());
+                    if let Some(symbol) = self.candle_chart.symbol().map(|s| s.to_string()) {
+                        ui.label(RichText::new(&symbol).size(16.0).monospace());
+                        self.candle_chart.show(ui);
+                        self.lot_table.show(ui, &symbol);
+                    }
                 }
                 Tab::History => {
                     self.history_chart.show(ui);
+                    self.drawdown_chart.set_range(self.history_chart.range());
+                    self.drawdown_chart.show(ui);
+                    self.risk_panel.set_range(self.history_chart.range());
+                    ui.separator();
+                    self.risk_panel.show(ui);
+                }
+                Tab::Balance => {
+                    self.performance.set_range(self.history_chart.range());
+                    self.performance.show(ui);
+                    self.balance_chart.show(ui);
+                    ui.separator();
+                    self.benchmark_chart.set_range(self.history_chart.range());
+                    self.benchmark_chart.show(ui);
+                }
+                Tab::Ledger => {
+                    if self.ledger_view.show(ui) {
+                        self.build_views();
+                    }
                 }
             });
+        self.sync_selection();
     }
 
     fn desktop_view(&mut self, ctx: &Context, _: &mut egui::Ui) {
@@ -121,7 +577,8 @@ impl WrapApp {
             .vscroll(false)
             .hscroll(false)
             .show(ctx, |ui| {
-                self.pie_chart.show(ui);
+                self.summary.show(ui);
+                self.show_allocation(ui);
             });
 
         egui::Window::new("Daily")
@@ -130,16 +587,102 @@ impl WrapApp {
             .hscroll(false)
             .resizable(false)
             .show(ctx, |ui| {
+                // the window does not scroll, so the rows do and a selection can be scrolled to
+                self.daily_table.set_scroll_height(Some(DAILY_HEIGHT));
                 self.daily_table.show(ui);
             });
 
+        self.candle_chart.select(self.daily_table.selected());
+        if let Some(symbol) = self.candle_chart.symbol().map(|s| s.to_string()) {
+            let mut open = true;
+            egui::Window::new(&symbol)
+                .id(egui::Id::new("candle"))
+                .open(&mut open)
+                .collapsible(false)
+                .vscroll(false)
+                .hscroll(false)
+                .show(ctx, |ui| {
+                    self.candle_chart.show(ui);
+                    if self.lot_table.contains(&symbol) {
+                        ui.separator();
+                        self.lot_table.show(ui, &symbol);
+                    }
+                });
+            if !open {
+                self.daily_table.select(None);
+            }
+        }
+
         egui::Window::new("Period")
             .collapsible(false)
             .vscroll(false)
             .hscroll(false)
             .show(ctx, |ui| {
                 self.history_chart.show(ui);
+                ui.separator();
+                self.drawdown_chart.set_range(self.history_chart.range());
+                self.drawdown_chart.show(ui);
+            });
+
+        self.risk_panel.set_range(self.history_chart.range());
+        egui::Window::new("Risk")
+            .collapsible(false)
+            .vscroll(false)
+            .hscroll(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                self.risk_panel.show(ui);
             });
+
+        self.benchmark_chart.set_range(self.history_chart.range());
+        egui::Window::new("Benchmark")
+            .collapsible(false)
+            .vscroll(false)
+            .hscroll(false)
+            .show(ctx, |ui| {
+                self.benchmark_chart.show(ui);
+            });
+
+        egui::Window::new("Balance")
+            .collapsible(false)
+            .vscroll(false)
+            .hscroll(false)
+            .show(ctx, |ui| {
+                self.balance_chart.show(ui);
+            });
+
+        self.performance.set_range(self.history_chart.range());
+        egui::Window::new("Performance")
+            .collapsible(false)
+            .vscroll(false)
+            .hscroll(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                self.performance.show(ui);
+            });
+
+        egui::Window::new("Rebalance")
+            .collapsible(true)
+            .vscroll(false)
+            .hscroll(false)
+            .default_open(false)
+            .show(ctx, |ui| {
+                self.rebalance.show(ui);
+            });
+
+        let mut ledger_changed = false;
+        egui::Window::new("Ledger")
+            .collapsible(true)
+            .vscroll(false)
+            .hscroll(false)
+            .default_open(false)
+            .show(ctx, |ui| {
+                ledger_changed = self.ledger_view.show(ui);
+            });
+        if ledger_changed {
+            self.build_views();
+        }
+        self.sync_selection();
     }
 
     fn login_check(&self) -> bool {
@@ -178,8 +721,8 @@ impl WrapApp {
                                 && ui.input(|i| i.key_pressed(egui::Key::Enter)))
                         {
                             if self.login_check() {
-                                self.load();
-                                self.app_state = AppState::LoggedIn;
+                                self.authenticated = true;
+                                self.load(ctx);
                             } else {
                                 self.input_password.clear();
                             }
@@ -188,6 +731,102 @@ impl WrapApp {
                 });
             });
     }
+
+    fn loading_view(&mut self, ctx: &Context, _: &mut egui::Ui) {
+        if let Some(result) = self.loader.as_ref().and_then(|loader| loader.poll()) {
+            self.loader = None;
+            match result {
+                Ok(pf_data) => self.apply(pf_data),
+                Err(err) => {
+                    log::error!("failed to load portfolio data: {}", err);
+                    match PortfolioRawData::embedded() {
+                        Some(Ok(pf_data)) => self.apply(pf_data),
+                        Some(Err(embedded_err)) => {
+                            log::error!("embedded portfolio data is invalid: {}", embedded_err);
+                            self.fail(err);
+                        }
+                        None => self.fail(err),
+                    }
+                }
+            }
+            return;
+        }
+
+        egui::Window::new("Loading")
+            .collapsible(false)
+            .vscroll(false)
+            .hscroll(false)
+            .resizable(false)
+            .fixed_size([270.0, 100.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label(RichText::new("Fetching portfolio...").monospace());
+                });
+            });
+    }
+
+    fn error_view(&mut self, ctx: &Context, _: &mut egui::Ui) {
+        let Some(err) = &self.load_error else {
+            self.app_state = AppState::BeforeLogin;
+            return;
+        };
+
+        let mut retry = false;
+        let mut back = false;
+        egui::Window::new("Error")
+            .collapsible(false)
+            .vscroll(false)
+            .hscroll(false)
+            .resizable(false)
+            .fixed_size([360.0, 160.0])
+            .show(ctx, |ui| {
+                ui.label(
+                    RichText::new(err.title())
+                        .size(16.0)
+                        .color(ui.visuals().error_fg_color),
+                );
+                ui.add_space(6.0);
+                egui::Grid::new("error_detail")
+                    .num_columns(2)
+                    .spacing([12.0, 4.0])
+                    .show(ui, |ui| {
+                        if let Some(path) = err.path() {
+                            ui.label("Field");
+                            ui.label(RichText::new(path).monospace());
+                            ui.end_row();
+                        }
+                        if let Some((line, column)) = err.position() {
+                            ui.label("Position");
+                            ui.label(
+                                RichText::new(format!("line {}, column {}", line, column))
+                                    .monospace(),
+                            );
+                            ui.end_row();
+                        }
+                        ui.label("Reason");
+                        ui.label(RichText::new(err.message()).monospace());
+                        ui.end_row();
+                    });
+                ui.add_space(6.0);
+                ui.horizontal(|ui| {
+                    back = ui.button("Back").clicked();
+                    // only a fetch behind the password may be retried, anything else goes back to login
+                    if self.authenticated {
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
+                            retry = ui.button("Retry").clicked();
+                        });
+                    }
+                });
+            });
+
+        if retry && self.authenticated {
+            self.load(ctx);
+        } else if back {
+            self.load_error = None;
+            self.app_state = AppState::BeforeLogin;
+        }
+    }
 }
 
 impl eframe::App for WrapApp {
@@ -195,6 +834,14 @@ impl eframe::App for WrapApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         let screen_size = ctx.screen_rect().size();
         let is_mobile = screen_size.x < screen_size.y;
+        let mut settings_changed = false;
+        // fonts are only loaded by the first frame, the bundled ones have no hangul
+        if !self.fonts_checked {
+            self.fonts_checked = true;
+            let hangul =
+                ctx.fonts(|fonts| fonts.has_glyphs(&egui::FontId::monospace(12.0), "만억조"));
+            settings_changed = self.settings.format.set_hangul(hangul);
+        }
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
             egui::menu::bar(ui, |menu_ui| {
                 // NOTE: no File->Quit on web pages!
@@ -217,37 +864,91 @@ impl eframe::App for WrapApp {
                     menu_ui.radio_value(&mut self.mobile_tab, Tab::Holdings, "PieChart");
                     menu_ui.radio_value(&mut self.mobile_tab, Tab::Daily, "Daily");
                     menu_ui.radio_value(&mut self.mobile_tab, Tab::History, "History");
+                    menu_ui.radio_value(&mut self.mobile_tab, Tab::Balance, "Balance");
+                    menu_ui.radio_value(&mut self.mobile_tab, Tab::Ledger, "Ledger");
+                }
+                menu_ui.separator();
+                if !self.fx.is_empty() {
+                    for currency in Currency::ALL {
+                        if menu_ui
+                            .radio_value(&mut self.settings.currency, currency, currency.label())
+                            .changed()
+                        {
+                            settings_changed = true;
+                        }
+                    }
+                    if let (true, Some((first, last))) = (self.fx_clamped, self.fx.span()) {
+                        menu_ui
+                            .label(
+                                RichText::new("⚠ rate clamped")
+                                    .color(menu_ui.visuals().warn_fg_color),
+                            )
+                            .on_hover_text(format!(
+                                "USD/KRW rates only cover {} to {}, other days use the nearest one",
+                                first, last
+                            ));
+                    }
+                    menu_ui.separator();
+                }
+                menu_ui.menu_button("Settings", |ui| {
+                    if self.settings.show(ui) {
+                        settings_changed = true;
+                    }
+                });
+                if !self.pf_data.skipped.is_empty() {
+                    let skipped = &self.pf_data.skipped;
+                    menu_ui.separator();
+                    menu_ui
+                        .label(
+                            RichText::new(format!("⚠ {} records skipped", skipped.len()))
+                                .color(menu_ui.visuals().warn_fg_color),
+                        )
+                        .on_hover_ui(|ui| {
+                            for err in skipped {
+                                ui.label(RichText::new(err.to_string()).monospace());
+                            }
+                        });
                 }
             });
         });
+        if settings_changed {
+            self.build_views();
+        }
 
-        egui::CentralPanel::default().show(ctx, |ui| {
-            match self.app_state {
-                AppState::BeforeLogin => {
-                    self.login_view(ctx, ui);
-                }
-                AppState::LoggedIn => {
-                    if is_mobile {
-                        self.mobile_view(ui);
-                    } else {
-                        self.desktop_view(ctx, ui);
-                    }
+        egui::CentralPanel::default().show(ctx, |ui| match self.app_state {
+            AppState::BeforeLogin => {
+                self.login_view(ctx, ui);
+            }
+            AppState::Loading => {
+                self.loading_view(ctx, ui);
+            }
+            AppState::Failed => {
+                self.error_view(ctx, ui);
+            }
+            AppState::LoggedIn => {
+                if is_mobile {
+                    self.mobile_view(ui);
+                } else {
+                    self.desktop_view(ctx, ui);
                 }
             }
-            // egui::Window::new("Dollar")
-            //     .title_bar(false)
-            //     .collapsible(false)
-            //     .vscroll(false)
-            //     .hscroll(false)
-            //     .resizable(false)
-            //     .show(ctx, |ui| {
-            //         self.line_chart.show(ui);
-            //     });
         });
     }
 
     /// Called by the framework to save state before shutdown.
-    fn save(&mut self, _storage: &mut dyn eframe::Storage) {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
         // eframe::set_value(storage, eframe::APP_KEY, self);
+        if self.authenticated {
+            eframe::set_value(storage, LEDGER_KEY, self.ledger_view.ledger());
+        } else if let Some(ledger) = &self.stored_ledger {
+            eframe::set_value(storage, LEDGER_KEY, ledger);
+        }
+        if self.authenticated {
+            eframe::set_value(storage, REBALANCE_KEY, &self.rebalance.saved());
+        } else if let Some(saved) = &self.stored_rebalance {
+            eframe::set_value(storage, REBALANCE_KEY, saved);
+        }
+        eframe::set_value(storage, SETTINGS_KEY, &self.settings);
+        eframe::set_value(storage, COLUMNS_KEY, self.daily_table.layout());
     }
 }
@@ -3,5 +3,6 @@
 mod app;
 
 mod modals;
+mod settings;
 
 pub use app::WrapApp;
@@ -0,0 +1,394 @@
+use crate::modals::calendar::{date_to_day, day_to_date, value_at};
+use crate::modals::colors::ColorScheme;
+use crate::modals::currency::{Currency, FxRates};
+use crate::modals::data_error::DataError;
+use crate::modals::data_reader::Record;
+use crate::modals::performance::window;
+use crate::modals::ranged_history::{span, RangedHistory};
+use crate::modals::risk::{mean, periods_per_year, wealth_index};
+use chrono::NaiveDate;
+use eframe::emath::Vec2;
+use egui::RichText;
+use egui_plot::{Legend, Line, Plot};
+use serde::{Deserialize, Serialize};
+
+/// One close of a market index, a csv row or an entry of `benchmarks` in the portfolio json.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct IndexClose {
+    pub symbol: String,
+    pub date: String,
+    pub close: f64,
+    /// What the index is quoted in, USD when missing.
+    #[serde(default)]
+    pub currency: Currency,
+}
+
+/// Closes of one index, oldest first.
+#[derive(Debug, Clone)]
+pub struct Benchmark {
+    pub symbol: String,
+    pub currency: Currency,
+    pub closes: Vec<(NaiveDate, f64)>,
+}
+
+impl Benchmark {
+    /// The series bundled in `assets/benchmarks.csv`.
+    pub fn bundled() -> Result<Vec<Benchmark>, DataError> {
+        let csv_file: &[u8] = include_bytes!("../../assets/benchmarks.csv");
+        Benchmark::from_csv(csv_file)
+    }
+
+    /// Parses rows like `NASDAQ,2024-06-14,17688.88,USD` under a `symbol,date,close,currency` header.
+    pub fn from_csv(csv_file: &[u8]) -> Result<Vec<Benchmark>, DataError> {
+        let mut reader = csv::Reader::from_reader(csv_file);
+        let closes = reader
+            .deserialize::<IndexClose>()
+            .map(|row| {
+                row.map_err(|err| {
+                    let line = err.position().map(|p| p.line() as usize).unwrap_or(0);
+                    DataError::Decode {
+                        line,
+                        column: 1,
+                        message: err.to_string(),
+                    }
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Benchmark::from_closes(&closes)
+    }
+
+    /// Groups the closes by symbol, in the order the symbols first appear.
+    /// A symbol takes the currency of its first close.
+    pub fn from_closes(closes: &[IndexClose]) -> Result<Vec<Benchmark>, DataError> {
+        let mut benchmarks: Vec<Benchmark> = vec![];
+        for (i, close) in closes.iter().enumerate() {
+            let date = NaiveDate::parse_from_str(&close.date, "%Y-%m-%d").map_err(|_| {
+                DataError::value(
+                    format!("benchmarks[{}].date", i),
+                    format!("`{}` is not a YYYY-MM-DD date", close.date),
+                )
+            })?;
+            if !close.close.is_finite() || close.close <= 0.0 {
+                return Err(DataError::value(
+                    format!("benchmarks[{}].close", i),
+                    format!("{} is not a valid close", close.close),
+                ));
+            }
+            match benchmarks.iter_mut().find(|b| b.symbol == close.symbol) {
+                Some(benchmark) => benchmark.closes.push((date, close.close)),
+                None => benchmarks.push(Benchmark {
+                    symbol: close.symbol.clone(),
+                    currency: close.currency,
+                    closes: vec![(date, close.close)],
+                }),
+            }
+        }
+        for benchmark in &mut benchmarks {
+            benchmark.closes.sort_by_key(|(date, _)| *date);
+        }
+        Ok(benchmarks)
+    }
+
+    /// Same series valued in `currency`, each close at the rate of its own day.
+    pub fn converted(&self, fx: &FxRates, currency: Currency) -> Benchmark {
+        Benchmark {
+            symbol: self.symbol.clone(),
+            currency,
+            closes: self
+                .closes
+                .iter()
+                .map(|(date, close)| {
+                    let factor = fx.between(self.currency, currency, Some(&date.to_string()));
+                    (*date, close * factor)
+                })
+                .collect(),
+        }
+    }
+
+    /// Replaces the series of the same symbol, or adds it.
+    pub fn merge(benchmarks: &mut Vec<Benchmark>, other: Vec<Benchmark>) {
+        for benchmark in other {
+            match benchmarks.iter_mut().find(|b| b.symbol == benchmark.symbol) {
+                Some(existing) => *existing = benchmark,
+                None => benchmarks.push(benchmark),
+            }
+        }
+    }
+}
+
+/// Return since the first value of the series.
+fn normalized(values: &[(NaiveDate, f64)]) -> Vec<(NaiveDate, f64)> {
+    let Some(&(_, first)) = values.first() else {
+        return vec![];
+    };
+    values
+        .iter()
+        .map(|(date, value)| (*date, value / first - 1.0))
+        .collect()
+}
+
+/// Regresses the portfolio's step returns on the benchmark's over the same dates.
+///
+/// `index` is the portfolio's wealth index. Beta is the slope, alpha the yearly return left
+/// over once beta times the benchmark's excess return over `risk_free` is taken out.
+pub fn alpha_beta(
+    index: &[(NaiveDate, f64)],
+    closes: &[(NaiveDate, f64)],
+    risk_free: f64,
+) -> Option<(f64, f64)> {
+    let (portfolio, benchmark): (Vec<f64>, Vec<f64>) = index
+        .windows(2)
+        .filter_map(|pair| {
+            let (d0, p0) = pair[0];
+            let (d1, p1) = pair[1];
+            let (_, b0) = value_at(closes, d0)?;
+            let (_, b1) = value_at(closes, d1)?;
+            (p0 > 0.0).then_some((p1 / p0 - 1.0, b1 / b0 - 1.0))
+        })
+        .unzip();
+    if portfolio.len() < 2 {
+        return None;
+    }
+    let (mean_p, mean_b) = (mean(&portfolio)?, mean(&benchmark)?);
+    let covariance: f64 = portfolio
+        .iter()
+        .zip(&benchmark)
+        .map(|(p, b)| (p - mean_p) * (b - mean_b))
+        .sum();
+    let variance: f64 = benchmark.iter().map(|b| (b - mean_b).powi(2)).sum();
+    if variance <= 0.0 {
+        return None;
+    }
+    let beta = covariance / variance;
+    let periods = periods_per_year(index)?;
+    let target = risk_free / periods;
+    let alpha = ((mean_p - target) - beta * (mean_b - target)) * periods;
+    Some((alpha, beta))
+}
+
+/// Figures of one benchmark over the selected range.
+#[derive(Debug, Clone)]
+struct Comparison {
+    symbol: String,
+    points: Vec<[f64; 2]>,
+    total: Option<f64>,
+    alpha_beta: Option<(f64, f64)>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct BenchmarkChart {
+    name: String,
+    history: RangedHistory,
+    risk_free: f64,
+    colors: ColorScheme,
+    #[serde(skip)]
+    benchmarks: Vec<Benchmark>,
+    #[serde(skip)]
+    portfolio: Option<Comparison>,
+    #[serde(skip)]
+    comparisons: Vec<Comparison>,
+}
+
+impl Default for BenchmarkChart {
+    fn default() -> Self {
+        BenchmarkChart::new("default", vec![], vec![], vec![], 0.0, ColorScheme::RedUp)
+    }
+}
+
+impl BenchmarkChart {
+    /// `risk_free` is the yearly rate in percent, as in the risk panel.
+    pub fn new<S: AsRef<str>>(
+        name: S,
+        data: Vec<Record>,
+        flows: Vec<Record>,
+        benchmarks: Vec<Benchmark>,
+        risk_free: f64,
+        colors: ColorScheme,
+    ) -> Self {
+        let mut chart = Self {
+            name: name.as_ref().to_string(),
+            history: RangedHistory::new(data, flows),
+            risk_free,
+            colors,
+            benchmarks,
+            portfolio: None,
+            comparisons: vec![],
+        };
+        chart.compute();
+        chart
+    }
+
+    pub fn set_range(&mut self, range: Option<(NaiveDate, NaiveDate)>) {
+        if self.history.set_range(range) {
+            self.compute();
+        }
+    }
+
+    /// Every series starts at zero on the first portfolio balance in range.
+    fn compute(&mut self) {
+        let (balances, flows) = self.history.parsed();
+        let Some((from, to)) = span(&balances) else {
+            self.portfolio = None;
+            self.comparisons = vec![];
+            return;
+        };
+        let to_points = |values: &[(NaiveDate, f64)]| -> Vec<[f64; 2]> {
+            normalized(values)
+                .into_iter()
+                .map(|(date, value)| [date_to_day(date), value])
+                .collect()
+        };
+
+        let index = wealth_index(&balances, &flows);
+        self.portfolio = Some(Comparison {
+            symbol: "Portfolio".to_string(),
+            points: to_points(&index),
+            total: index.last().map(|(_, value)| value - 1.0),
+            alpha_beta: None,
+        });
+        self.comparisons = self
+            .benchmarks
+            .iter()
+            .map(|benchmark| {
+                let closes = window(&benchmark.closes, from, to);
+                let points = to_points(&closes);
+                Comparison {
+                    symbol: benchmark.symbol.clone(),
+                    total: points.last().map(|[_, value]| *value),
+                    points,
+                    alpha_beta: alpha_beta(&index, &benchmark.closes, self.risk_free / 100.0),
+                }
+            })
+            .collect();
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui) {
+        let Some(portfolio) = &self.portfolio else {
+            ui.label(RichText::new("Not enough history").size(12.0).monospace());
+            return;
+        };
+
+        Plot::new(self.name.clone())
+            .legend(Legend::default())
+            .show_grid(false)
+            .show_background(false)
+            .set_margin_fraction(Vec2 { x: 0.05, y: 0.1 })
+            .y_axis_formatter(|value, _, _| format!("{:.0}%", value.value * 100.0))
+            .x_axis_formatter(|value, _, _| {
+                if value.value.fract() != 0.0 {
+                    return "".to_string();
+                }
+                day_to_date(value.value)
+                    .map(|date| date.format("%Y-%m-%d").to_string())
+                    .unwrap_or_default()
+            })
+            .label_formatter(|name, value| {
+                if name.is_empty() {
+                    return "".to_string();
+                }
+                let date = day_to_date(value.x.round())
+                    .map(|date| date.format("%Y-%m-%d").to_string())
+                    .unwrap_or_default();
+                format!("{}\n{}\n{:+.2}%", name, date, value.y * 100.0)
+            })
+            .view_aspect(1.6)
+            .show(ui, |plot_ui| {
+                for series in std::iter::once(portfolio).chain(&self.comparisons) {
+                    let line = Line::new(series.points.clone()).name(&series.symbol);
+                    plot_ui.line(line);
+                }
+            });
+
+        ui.separator();
+        let text = |text: String| RichText::new(text).size(12.0).monospace();
+        let percent = |value: Option<f64>| match value {
+            Some(value) => text(format!("{:+.2}%", value * 100.0)).color(self.colors.of(value)),
+            None => text("-".to_string()),
+        };
+        egui::Grid::new(format!("{}_grid", self.name))
+            .num_columns(4)
+            .spacing([24.0, 2.0])
+            .show(ui, |ui| {
+                ui.label(text("".to_string()));
+                ui.label(text("Return".to_string()));
+                ui.label(text("Alpha".to_string()))
+                    .on_hover_text("Yearly return beyond what beta explains");
+                ui.label(text("Beta".to_string()));
+                ui.end_row();
+
+                ui.label(text(portfolio.symbol.clone()));
+                ui.label(percent(portfolio.total));
+                ui.end_row();
+
+                for comparison in &self.comparisons {
+                    ui.label(text(comparison.symbol.clone()));
+                    ui.label(percent(comparison.total));
+                    ui.label(percent(comparison.alpha_beta.map(|(alpha, _)| alpha)));
+                    ui.label(match comparison.alpha_beta {
+                        Some((_, beta)) => text(format!("{:.2}", beta)),
+                        None => text("-".to_string()),
+                    });
+                    ui.end_row();
+                }
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modals::test_util::date;
+
+    #[test]
+    fn csv_groups_by_symbol() {
+        let csv = b"symbol,date,close\nA,2024-01-02,2\nB,2024-01-01,5\nA,2024-01-01,1\n";
+        let benchmarks = Benchmark::from_csv(csv).unwrap();
+        assert_eq!(benchmarks.len(), 2);
+        assert_eq!(benchmarks[0].symbol, "A");
+        assert_eq!(benchmarks[0].closes[0], (date("2024-01-01"), 1.0));
+        assert!(Benchmark::from_csv(b"symbol,date,close\nA,01/02/2024,2\n").is_err());
+    }
+
+    #[test]
+    fn alpha_beta_of_a_leveraged_copy() {
+        let dates = [
+            "2023-01-01",
+            "2023-04-01",
+            "2023-07-01",
+            "2023-10-01",
+            "2024-01-01",
+        ];
+        let returns = [0.05, -0.02, 0.03, 0.01];
+        let mut closes = vec![(date(dates[0]), 100.0)];
+        let mut index = vec![(date(dates[0]), 1.0)];
+        for (i, r) in returns.iter().enumerate() {
+            let b = closes[i].1 * (1.0 + r);
+            let p = index[i].1 * (1.0 + 2.0 * r);
+            closes.push((date(dates[i + 1]), b));
+            index.push((date(dates[i + 1]), p));
+        }
+        let (alpha, beta) = alpha_beta(&index, &closes, 0.0).unwrap();
+        assert!((beta - 2.0).abs() < 1e-9);
+        assert!(alpha.abs() < 1e-9);
+    }
+
+    #[test]
+    fn indexes_convert_from_their_own_currency() {
+        let csv = b"symbol,date,close,currency\nK,2024-04-15,2772,KRW\nN,2024-04-15,16000,USD\n";
+        let benchmarks = Benchmark::from_csv(csv).unwrap();
+        assert_eq!(benchmarks[0].currency, Currency::Krw);
+        assert_eq!(benchmarks[1].currency, Currency::Usd);
+
+        let fx = FxRates::from_csv(b"Date,Rate\n\"4/15/2024 Monday\",\"1,386\"\n").unwrap();
+        let kospi = &benchmarks[0];
+        assert_eq!(kospi.converted(&fx, Currency::Krw).closes[0].1, 2772.0);
+        assert_eq!(kospi.converted(&fx, Currency::Usd).closes[0].1, 2.0);
+        let nasdaq = &benchmarks[1];
+        assert_eq!(nasdaq.converted(&fx, Currency::Usd).closes[0].1, 16000.0);
+        assert_eq!(
+            nasdaq.converted(&fx, Currency::Krw).closes[0].1,
+            16000.0 * 1386.0
+        );
+    }
+}
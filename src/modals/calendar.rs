@@ -0,0 +1,307 @@
+use crate::modals::data_reader::Record;
+use chrono::{Datelike, Days, Months, NaiveDate, Weekday};
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, PartialEq, Debug)]
+pub enum Period {
+    Daily,
+    Weekly,
+    Monthly,
+    Quarterly,
+    Yearly,
+}
+
+impl Period {
+    pub const ALL: [Period; 5] = [
+        Period::Daily,
+        Period::Weekly,
+        Period::Monthly,
+        Period::Quarterly,
+        Period::Yearly,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Period::Daily => "Daily",
+            Period::Weekly => "Weekly",
+            Period::Monthly => "Monthly",
+            Period::Quarterly => "Quarterly",
+            Period::Yearly => "Yearly",
+        }
+    }
+
+    /// First day of the bucket containing `date`, weeks start on Monday.
+    pub fn start_of(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Period::Daily => date,
+            Period::Weekly => date
+                .checked_sub_days(Days::new(date.weekday().num_days_from_monday() as u64))
+                .unwrap_or(date),
+            Period::Monthly => date.with_day(1).unwrap_or(date),
+            Period::Quarterly => {
+                let month = (date.month0() / 3) * 3 + 1;
+                NaiveDate::from_ymd_opt(date.year(), month, 1).unwrap_or(date)
+            }
+            Period::Yearly => NaiveDate::from_ymd_opt(date.year(), 1, 1).unwrap_or(date),
+        }
+    }
+
+    /// Start of the bucket `n` buckets before the one starting at `start`.
+    pub fn back(&self, start: NaiveDate, n: u32) -> Option<NaiveDate> {
+        match self {
+            Period::Daily => start.checked_sub_days(Days::new(n as u64)),
+            Period::Weekly => start.checked_sub_days(Days::new(7 * n as u64)),
+            Period::Monthly => start.checked_sub_months(Months::new(n)),
+            Period::Quarterly => start.checked_sub_months(Months::new(3 * n)),
+            Period::Yearly => start.checked_sub_months(Months::new(12 * n)),
+        }
+    }
+
+    /// Last day of the bucket starting at `start`.
+    pub fn end_of(&self, start: NaiveDate) -> NaiveDate {
+        let next = match self {
+            Period::Daily => start.checked_add_days(Days::new(1)),
+            Period::Weekly => start.checked_add_days(Days::new(7)),
+            Period::Monthly => start.checked_add_months(Months::new(1)),
+            Period::Quarterly => start.checked_add_months(Months::new(3)),
+            Period::Yearly => start.checked_add_months(Months::new(12)),
+        };
+        next.and_then(|next| next.pred_opt()).unwrap_or(start)
+    }
+
+    /// Number of buckets from the one holding `from` to the one holding `to`, both included.
+    pub fn count_between(&self, from: NaiveDate, to: NaiveDate) -> usize {
+        let first = self.start_of(from);
+        let mut start = self.start_of(to);
+        let mut count = 1;
+        while start > first {
+            match self.back(start, 1) {
+                Some(previous) => start = previous,
+                None => break,
+            }
+            count += 1;
+        }
+        count
+    }
+
+    pub fn format(&self, start: NaiveDate) -> String {
+        match self {
+            Period::Daily => start.format("%Y-%m-%d").to_string(),
+            Period::Weekly => format!(
+                "{}-W{:02}",
+                start.iso_week().year(),
+                start.iso_week().week()
+            ),
+            Period::Monthly => start.format("%Y-%m").to_string(),
+            Period::Quarterly => format!("{} Q{}", start.year(), start.month0() / 3 + 1),
+            Period::Yearly => start.format("%Y").to_string(),
+        }
+    }
+}
+
+/// Shortcuts for the visible date range, counted back from the latest record.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, PartialEq, Debug)]
+pub enum RangePreset {
+    Ytd,
+    OneYear,
+    ThreeYears,
+    All,
+}
+
+impl RangePreset {
+    pub const ALL: [RangePreset; 4] = [
+        RangePreset::Ytd,
+        RangePreset::OneYear,
+        RangePreset::ThreeYears,
+        RangePreset::All,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            RangePreset::Ytd => "YTD",
+            RangePreset::OneYear => "1Y",
+            RangePreset::ThreeYears => "3Y",
+            RangePreset::All => "All",
+        }
+    }
+
+    /// First day of the range ending at `latest`, never before `first`.
+    pub fn from(&self, first: NaiveDate, latest: NaiveDate) -> NaiveDate {
+        let from = match self {
+            RangePreset::Ytd => NaiveDate::from_ymd_opt(latest.year(), 1, 1),
+            RangePreset::OneYear => latest.checked_sub_months(Months::new(12)),
+            RangePreset::ThreeYears => latest.checked_sub_months(Months::new(36)),
+            RangePreset::All => Some(first),
+        };
+        from.unwrap_or(first).max(first)
+    }
+}
+
+/// One calendar bucket and the balance it closed with.
+#[derive(Debug, Clone)]
+pub struct Bucket {
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    pub label: String,
+    /// Date of the record the value was taken from, which may lie in an earlier bucket.
+    pub date: Option<NaiveDate>,
+    pub value: Option<f64>,
+}
+
+/// Days since the Unix epoch, so one unit on a plot's x axis is one day.
+pub fn date_to_day(date: NaiveDate) -> f64 {
+    (date - NaiveDate::default()).num_days() as f64
+}
+
+pub fn day_to_date(day: f64) -> Option<NaiveDate> {
+    NaiveDate::default().checked_add_days(Days::new(u64::try_from(day as i64).ok()?))
+}
+
+/// Dated balances sorted oldest first, records with unreadable dates are dropped.
+pub fn parse_records(records: &[Record]) -> Vec<(NaiveDate, f64)> {
+    let mut parsed: Vec<_> = records
+        .iter()
+        .filter_map(|record| {
+            NaiveDate::parse_from_str(&record.date, "%Y-%m-%d")
+                .ok()
+                .map(|date| (date, record.balance))
+        })
+        .collect();
+    parsed.sort_by_key(|(date, _)| *date);
+    parsed
+}
+
+/// Value of the last record on or before `date`.
+pub fn value_at(records: &[(NaiveDate, f64)], date: NaiveDate) -> Option<(NaiveDate, f64)> {
+    let index = records.partition_point(|(d, _)| *d <= date);
+    index.checked_sub(1).map(|index| records[index])
+}
+
+/// New York session a quote fetched in Korea on `date` closed in, the last weekday before it.
+pub fn trading_day(date: NaiveDate) -> NaiveDate {
+    let back = match date.weekday() {
+        Weekday::Mon => 3,
+        Weekday::Sun => 2,
+        _ => 1,
+    };
+    date.checked_sub_days(Days::new(back)).unwrap_or(date)
+}
+
+/// Splits the calendar into `count` consecutive buckets of `period`, the last one holding `end`.
+///
+/// Each bucket takes the last value available at its end, carrying the previous one forward
+/// over weekends and missing days. Buckets before the first record have no value.
+pub fn bucketize(
+    records: &[(NaiveDate, f64)],
+    period: Period,
+    count: usize,
+    end: NaiveDate,
+) -> Vec<Bucket> {
+    let last = period.start_of(end);
+    (0..count as u32)
+        .rev()
+        .filter_map(|n| period.back(last, n))
+        .map(|start| {
+            let bucket_end = period.end_of(start).min(end);
+            let found = value_at(records, bucket_end);
+            Bucket {
+                start,
+                end: bucket_end,
+                label: period.format(start),
+                date: found.map(|(date, _)| date),
+                value: found.map(|(_, value)| value),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modals::test_util::date;
+
+    #[test]
+    fn quotes_belong_to_the_previous_session() {
+        // 2024-06-16 is a sunday, 2024-06-17 a monday
+        assert_eq!(trading_day(date("2024-06-16")), date("2024-06-14"));
+        assert_eq!(trading_day(date("2024-06-17")), date("2024-06-14"));
+        assert_eq!(trading_day(date("2024-06-19")), date("2024-06-18"));
+    }
+
+    #[test]
+    fn steps_back_over_month_and_year_boundaries() {
+        assert_eq!(
+            Period::Monthly.back(date("2024-01-01"), 1),
+            Some(date("2023-12-01"))
+        );
+        assert_eq!(
+            Period::Quarterly.back(date("2024-01-01"), 1),
+            Some(date("2023-10-01"))
+        );
+        assert_eq!(
+            Period::Weekly.start_of(date("2024-01-03")),
+            date("2024-01-01")
+        );
+        assert_eq!(
+            Period::Weekly.start_of(date("2023-01-01")),
+            date("2022-12-26")
+        );
+        // 31 days in march, 30 in april, 29 in february 2024
+        assert_eq!(
+            Period::Monthly.end_of(date("2024-03-01")),
+            date("2024-03-31")
+        );
+        assert_eq!(
+            Period::Monthly.end_of(date("2024-04-01")),
+            date("2024-04-30")
+        );
+        assert_eq!(
+            Period::Monthly.end_of(date("2024-02-01")),
+            date("2024-02-29")
+        );
+        assert_eq!(
+            Period::Monthly.count_between(date("2023-11-30"), date("2024-02-01")),
+            4
+        );
+        assert_eq!(
+            Period::Yearly.count_between(date("2022-12-31"), date("2024-01-01")),
+            3
+        );
+        assert_eq!(
+            Period::Daily.count_between(date("2024-02-28"), date("2024-03-01")),
+            3
+        );
+    }
+
+    #[test]
+    fn buckets_carry_values_over_weekends() {
+        // friday and the following tuesday only
+        let records = [(date("2024-05-31"), 1.0), (date("2024-06-04"), 2.0)];
+        let buckets = bucketize(&records, Period::Daily, 5, date("2024-06-04"));
+        let values: Vec<_> = buckets.iter().map(|b| b.value).collect();
+        assert_eq!(
+            values,
+            [Some(1.0), Some(1.0), Some(1.0), Some(1.0), Some(2.0)]
+        );
+        assert_eq!(buckets[1].date, Some(date("2024-05-31")));
+
+        let buckets = bucketize(&records, Period::Monthly, 3, date("2024-06-04"));
+        let labels: Vec<_> = buckets.iter().map(|b| b.label.as_str()).collect();
+        assert_eq!(labels, ["2024-04", "2024-05", "2024-06"]);
+        assert_eq!(buckets[0].value, None);
+        assert_eq!(buckets[1].end, date("2024-05-31"));
+        assert_eq!(buckets[2].end, date("2024-06-04"));
+    }
+
+    #[test]
+    fn earliest_dates_do_not_underflow() {
+        let first = NaiveDate::MIN;
+        for period in Period::ALL {
+            let start = period.start_of(first);
+            assert_eq!(period.back(start, 1), None);
+            assert_eq!(period.count_between(first, first), 1);
+            let buckets = bucketize(&[(first, 1.0)], period, 3, first);
+            assert_eq!(buckets.len(), 1);
+            assert_eq!(buckets[0].value, Some(1.0));
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use std::collections::BTreeMap;
+
+use crate::modals::colors::ColorScheme;
+use crate::modals::currency::Currency;
+use crate::modals::format::NumberFormat;
+use eframe::emath::Vec2;
+use egui::Stroke;
+use egui_plot::{BoxElem, BoxPlot, BoxSpread, Plot};
+
+#[derive(serde::Deserialize, serde::Serialize, Clone)]
+pub struct Candle {
+    pub date: String,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct CandleChart {
+    name: String,
+    series: BTreeMap<String, Vec<Candle>>,
+    symbol: Option<String>,
+    currency: Currency,
+    colors: ColorScheme,
+    format: NumberFormat,
+}
+
+impl Default for CandleChart {
+    fn default() -> Self {
+        Self {
+            name: "default".to_string(),
+            series: BTreeMap::new(),
+            symbol: None,
+            currency: Currency::Usd,
+            colors: ColorScheme::RedUp,
+            format: NumberFormat::default(),
+        }
+    }
+}
+
+impl CandleChart {
+    pub fn new<S: AsRef<str>>(
+        name: S,
+        series: BTreeMap<String, Vec<Candle>>,
+        currency: Currency,
+        colors: ColorScheme,
+        format: NumberFormat,
+    ) -> Self {
+        Self {
+            name: name.as_ref().to_string(),
+            series,
+            symbol: None,
+            currency,
+            colors,
+            format,
+        }
+    }
+
+    pub fn symbol(&self) -> Option<&str> {
+        self.symbol.as_deref()
+    }
+
+    pub fn select(&mut self, symbol: Option<&str>) {
+        self.symbol = symbol
+            .filter(|symbol| self.series.contains_key(*symbol))
+            .map(|symbol| symbol.to_string());
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui) {
+        let Some(candles) = self.symbol.as_ref().and_then(|s| self.series.get(s)) else {
+            return;
+        };
+
+        let amount = |value: f64| self.format.amount(value, self.currency);
+        let x_axis: Vec<String> = candles.iter().map(|c| c.date.clone()).collect();
+        let boxes: Vec<_> = candles
+            .iter()
+            .enumerate()
+            .map(|(i, candle)| {
+                let color = if candle.close >= candle.open {
+                    self.colors.up()
+                } else {
+                    self.colors.down()
+                };
+                let spread = BoxSpread::new(
+                    candle.low,
+                    candle.open.min(candle.close),
+                    candle.close,
+                    candle.open.max(candle.close),
+                    candle.high,
+                );
+                BoxElem::new(i as f64, spread)
+                    .name(format!(
+                        "{}\nO {}\nH {}\nL {}\nC {}",
+                        candle.date,
+                        amount(candle.open),
+                        amount(candle.high),
+                        amount(candle.low),
+                        amount(candle.close)
+                    ))
+                    .box_width(0.6)
+                    .whisker_width(0.0)
+                    .stroke(Stroke::new(1.0, color))
+                    .fill(color.linear_multiply(0.5))
+            })
+            .collect();
+
+        Plot::new(format!(
+            "{}_{}",
+            self.name,
+            self.symbol.as_deref().unwrap_or("")
+        ))
+        .show_background(false)
+        .show_grid(false)
+        .allow_scroll(false)
+        .set_margin_fraction(Vec2 { x: 0.15, y: 0.07 })
+        .x_axis_formatter(move |value, _, _| {
+            if value.value < 0.0 || value.value.fract() != 0.0 {
+                return "".to_string();
+            }
+            x_axis
+                .get(value.value as usize)
+                .unwrap_or(&"".to_string())
+                .clone()
+        })
+        .view_aspect(1.4)
+        .show(ui, |plot_ui| {
+            plot_ui.box_plot(
+                BoxPlot::new(boxes).element_formatter(Box::new(|elem, _| elem.name.clone())),
+            );
+        });
+    }
+}
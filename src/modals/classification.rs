@@ -0,0 +1,153 @@
+use crate::modals::data_error::DataError;
+use crate::modals::data_reader::Stock;
+
+const UNCLASSIFIED: &str = "Unclassified";
+
+/// What the allocation is grouped by.
+#[derive(Debug, serde::Deserialize, serde::Serialize, Clone, Copy, PartialEq)]
+pub enum Dimension {
+    Symbol,
+    Sector,
+    Industry,
+    Country,
+    AssetClass,
+    Account,
+}
+
+impl Dimension {
+    pub const ALL: [Dimension; 6] = [
+        Dimension::Symbol,
+        Dimension::Sector,
+        Dimension::Industry,
+        Dimension::Country,
+        Dimension::AssetClass,
+        Dimension::Account,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Dimension::Symbol => "Symbol",
+            Dimension::Sector => "Sector",
+            Dimension::Industry => "Industry",
+            Dimension::Country => "Country",
+            Dimension::AssetClass => "Asset class",
+            Dimension::Account => "Account",
+        }
+    }
+
+    /// Group `stock` falls in, holdings without the field share one group.
+    pub fn group_of(&self, stock: &Stock) -> String {
+        let field = match self {
+            Dimension::Symbol => return stock.symbol.clone(),
+            Dimension::Sector => &stock.sector,
+            Dimension::Industry => &stock.industry,
+            Dimension::Country => &stock.country,
+            Dimension::AssetClass => &stock.asset_class,
+            Dimension::Account => &stock.account,
+        };
+        field
+            .as_deref()
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .unwrap_or(UNCLASSIFIED)
+            .to_string()
+    }
+}
+
+/// Classification of one symbol from the side-car file.
+#[derive(Debug, serde::Deserialize, serde::Serialize, Clone, Default)]
+#[serde(default)]
+pub struct Classification {
+    pub symbol: String,
+    pub sector: Option<String>,
+    pub industry: Option<String>,
+    pub country: Option<String>,
+    pub asset_class: Option<String>,
+    pub account: Option<String>,
+}
+
+impl Classification {
+    /// The mapping bundled in `assets/classification.csv`.
+    pub fn bundled() -> Result<Vec<Classification>, DataError> {
+        let csv_file: &[u8] = include_bytes!("../../assets/classification.csv");
+        Classification::from_csv(csv_file)
+    }
+
+    /// Parses rows under a `symbol,sector,industry,country,asset_class,account` header,
+    /// empty cells leave the field unknown.
+    pub fn from_csv(csv_file: &[u8]) -> Result<Vec<Classification>, DataError> {
+        let mut reader = csv::Reader::from_reader(csv_file);
+        reader
+            .deserialize::<Classification>()
+            .map(|row| {
+                row.map_err(|err| {
+                    let line = err.position().map(|p| p.line() as usize).unwrap_or(0);
+                    DataError::Decode {
+                        line,
+                        column: 1,
+                        message: err.to_string(),
+                    }
+                })
+            })
+            .collect()
+    }
+
+    /// Fills the fields `stock` does not carry itself, the portfolio data wins over the file.
+    pub fn apply(mappings: &[Classification], stock: &mut Stock) {
+        let Some(mapping) = mappings.iter().find(|m| m.symbol == stock.symbol) else {
+            return;
+        };
+        let fields = [
+            (&mut stock.sector, &mapping.sector),
+            (&mut stock.industry, &mapping.industry),
+            (&mut stock.country, &mapping.country),
+            (&mut stock.asset_class, &mapping.asset_class),
+            (&mut stock.account, &mapping.account),
+        ];
+        for (field, value) in fields {
+            if field.is_none() {
+                field.clone_from(value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stock(symbol: &str, sector: Option<&str>) -> Stock {
+        let mut stock: Stock = serde_json::from_value(serde_json::json!({
+            "symbol": symbol,
+            "owned": 1,
+            "cost": 1.0,
+            "company_name": symbol,
+            "close_price": 1.0,
+            "highest_price": 1.0,
+            "open_price": 1.0,
+            "lowest_price": 1.0,
+            "yesterday_price": 1.0,
+        }))
+        .unwrap();
+        stock.sector = sector.map(|s| s.to_string());
+        stock
+    }
+
+    #[test]
+    fn data_wins_over_the_mapping() {
+        let mappings = Classification::from_csv(
+            b"symbol,sector,industry,country,asset_class,account\nA,Tech,,US,,\n",
+        )
+        .unwrap();
+        let mut own = stock("A", Some("Energy"));
+        Classification::apply(&mappings, &mut own);
+        assert_eq!(own.sector.as_deref(), Some("Energy"));
+        assert_eq!(own.country.as_deref(), Some("US"));
+        assert_eq!(Dimension::Industry.group_of(&own), UNCLASSIFIED);
+
+        let mut unknown = stock("B", None);
+        Classification::apply(&mappings, &mut unknown);
+        assert_eq!(Dimension::Sector.group_of(&unknown), UNCLASSIFIED);
+        assert_eq!(Dimension::Symbol.group_of(&unknown), "B");
+    }
+}
@@ -0,0 +1,70 @@
+use egui::Color32;
+
+/// Okabe-Ito colours, told apart under the common forms of colour blindness.
+const OKABE_ITO: [Color32; 7] = [
+    Color32::from_rgb(0, 114, 178),
+    Color32::from_rgb(230, 159, 0),
+    Color32::from_rgb(0, 158, 115),
+    Color32::from_rgb(204, 121, 167),
+    Color32::from_rgb(86, 180, 233),
+    Color32::from_rgb(213, 94, 0),
+    Color32::from_rgb(240, 228, 66),
+];
+
+/// How gains and losses are coloured across the app.
+#[derive(Debug, serde::Deserialize, serde::Serialize, Clone, Copy, PartialEq)]
+pub enum ColorScheme {
+    /// Korean market convention.
+    RedUp,
+    GreenUp,
+    ColorblindSafe,
+}
+
+impl ColorScheme {
+    pub const ALL: [ColorScheme; 3] = [
+        ColorScheme::RedUp,
+        ColorScheme::GreenUp,
+        ColorScheme::ColorblindSafe,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ColorScheme::RedUp => "Red up, blue down",
+            ColorScheme::GreenUp => "Green up, red down",
+            ColorScheme::ColorblindSafe => "Colorblind safe",
+        }
+    }
+
+    pub fn up(&self) -> Color32 {
+        match self {
+            ColorScheme::RedUp => Color32::RED,
+            ColorScheme::GreenUp => Color32::GREEN,
+            ColorScheme::ColorblindSafe => OKABE_ITO[4],
+        }
+    }
+
+    pub fn down(&self) -> Color32 {
+        match self {
+            ColorScheme::RedUp => Color32::LIGHT_BLUE,
+            ColorScheme::GreenUp => Color32::RED,
+            ColorScheme::ColorblindSafe => OKABE_ITO[1],
+        }
+    }
+
+    /// Colour of a change by `value`, anything not above zero reads as a loss.
+    pub fn of(&self, value: f64) -> Color32 {
+        if value > 0.0 {
+            self.up()
+        } else {
+            self.down()
+        }
+    }
+
+    /// Distinct colours for categories such as pie slices, `None` leaves the choice to the caller.
+    pub fn category(&self, index: usize) -> Option<Color32> {
+        match self {
+            ColorScheme::ColorblindSafe => OKABE_ITO.get(index).copied(),
+            _ => None,
+        }
+    }
+}
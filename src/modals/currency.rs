@@ -0,0 +1,182 @@
+use crate::modals::data_error::DataError;
+use chrono::NaiveDate;
+
+/// Portfolio figures are stored in USD and converted for display.
+#[derive(Debug, serde::Deserialize, serde::Serialize, Clone, Copy, PartialEq, Default)]
+pub enum Currency {
+    #[default]
+    #[serde(alias = "USD")]
+    Usd,
+    #[serde(alias = "KRW")]
+    Krw,
+}
+
+impl Currency {
+    pub const ALL: [Currency; 2] = [Currency::Usd, Currency::Krw];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Currency::Usd => "USD",
+            Currency::Krw => "KRW",
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Currency::Usd => "$",
+            Currency::Krw => "₩",
+        }
+    }
+
+    /// Digits after the decimal point worth showing for a price.
+    pub fn decimals(&self) -> usize {
+        match self {
+            Currency::Usd => 2,
+            Currency::Krw => 0,
+        }
+    }
+}
+
+/// Daily USD/KRW rates, oldest first.
+#[derive(Debug, Clone, Default)]
+pub struct FxRates {
+    rates: Vec<(NaiveDate, f64)>,
+}
+
+impl FxRates {
+    /// The series bundled in `assets/usd-krw.csv`.
+    pub fn bundled() -> Result<Self, DataError> {
+        let csv_file: &[u8] = include_bytes!("../../assets/usd-krw.csv");
+        FxRates::from_csv(csv_file)
+    }
+
+    /// Parses rows like `"4/15/2024 Monday","1,386.10492",USD,KRW`.
+    pub fn from_csv(csv_file: &[u8]) -> Result<Self, DataError> {
+        let mut reader = csv::Reader::from_reader(csv_file);
+        let mut rates = vec![];
+        for (i, record) in reader.records().enumerate() {
+            let record = record.map_err(|err| {
+                let (line, column) = err
+                    .position()
+                    .map(|p| (p.line() as usize, 1))
+                    .unwrap_or((0, 0));
+                DataError::Decode {
+                    line,
+                    column,
+                    message: err.to_string(),
+                }
+            })?;
+            let field = |index: usize| record.get(index).unwrap_or("").trim();
+
+            let date = field(0).split_whitespace().next().unwrap_or("");
+            let date = NaiveDate::parse_from_str(date, "%m/%d/%Y").map_err(|_| {
+                DataError::value(
+                    format!("rates[{}].Date", i),
+                    format!("`{}` is not a M/D/YYYY date", field(0)),
+                )
+            })?;
+            let rate = field(1).replace(',', "").parse::<f64>().map_err(|_| {
+                DataError::value(
+                    format!("rates[{}].Rate", i),
+                    format!("`{}` is not a number", field(1)),
+                )
+            })?;
+            if !rate.is_finite() || rate <= 0.0 {
+                return Err(DataError::value(
+                    format!("rates[{}].Rate", i),
+                    format!("{} is not a valid rate", rate),
+                ));
+            }
+            rates.push((date, rate));
+        }
+        rates.sort_by_key(|(date, _)| *date);
+        Ok(Self { rates })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rates.is_empty()
+    }
+
+    pub fn latest(&self) -> Option<f64> {
+        self.rates.last().map(|(_, rate)| *rate)
+    }
+
+    /// First and last day with a published rate.
+    pub fn span(&self) -> Option<(NaiveDate, NaiveDate)> {
+        Some((self.rates.first()?.0, self.rates.last()?.0))
+    }
+
+    /// Whether `date` lies within the series, other days get the nearest rate instead.
+    pub fn covers(&self, date: &str) -> bool {
+        match (self.span(), NaiveDate::parse_from_str(date, "%Y-%m-%d")) {
+            (Some((first, last)), Ok(date)) => first <= date && date <= last,
+            _ => false,
+        }
+    }
+
+    /// The last rate published on or before `date`, or the first one for earlier dates.
+    pub fn rate_on(&self, date: NaiveDate) -> Option<f64> {
+        let index = self.rates.partition_point(|(d, _)| *d <= date);
+        self.rates
+            .get(index.saturating_sub(1))
+            .map(|(_, rate)| *rate)
+    }
+
+    /// Same as `rate_on`, for the `YYYY-MM-DD` strings used in the portfolio data.
+    pub fn rate_on_str(&self, date: &str) -> Option<f64> {
+        match NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+            Ok(date) => self.rate_on(date),
+            Err(_) => self.latest(),
+        }
+    }
+
+    /// Multiplier turning an amount in `from` into `to`.
+    pub fn between(&self, from: Currency, to: Currency, date: Option<&str>) -> f64 {
+        self.factor(to, date) / self.factor(from, date)
+    }
+
+    /// Multiplier turning a USD amount into `currency`.
+    pub fn factor(&self, currency: Currency, date: Option<&str>) -> f64 {
+        match currency {
+            Currency::Usd => 1.0,
+            Currency::Krw => date
+                .and_then(|date| self.rate_on_str(date))
+                .or(self.latest())
+                .unwrap_or(1.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modals::test_util::date;
+
+    #[test]
+    fn parses_quoted_dates_and_grouped_numbers() {
+        let csv = br#"Date,Rate,"ISO Code From","ISO Code To"
+"4/16/2024 Tuesday","1,389.5",USD,KRW
+"4/15/2024 Monday","1,386.25",USD,KRW
+"#;
+        let fx = FxRates::from_csv(csv).unwrap();
+        assert_eq!(fx.span(), Some((date("2024-04-15"), date("2024-04-16"))));
+        assert_eq!(fx.rate_on(date("2024-04-15")), Some(1386.25));
+        assert_eq!(fx.latest(), Some(1389.5));
+
+        let err = FxRates::from_csv(b"Date,Rate\n\"2024-04-15\",1\n").unwrap_err();
+        assert_eq!(err.path(), Some("rates[0].Date"));
+    }
+
+    #[test]
+    fn out_of_range_dates_clamp_to_the_nearest_rate() {
+        let fx = FxRates::bundled().unwrap();
+        assert_eq!(fx.span(), Some((date("2024-04-15"), date("2024-05-28"))));
+        assert_eq!(
+            fx.rate_on(date("2024-01-01")),
+            fx.rate_on(date("2024-04-15"))
+        );
+        assert_eq!(fx.rate_on(date("2024-06-16")), fx.latest());
+        assert!(fx.covers("2024-05-01"));
+        assert!(!fx.covers("2024-06-16"));
+    }
+}
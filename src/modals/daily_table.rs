@@ -1,118 +1,635 @@
+use crate::modals::candle_chart::Candle;
+use crate::modals::colors::ColorScheme;
+use crate::modals::currency::Currency;
+use crate::modals::data_reader;
+use crate::modals::format::NumberFormat;
+use crate::modals::line_chart::sparkline;
+use chrono::{Months, NaiveDate};
+use egui::{Align, RichText, Stroke};
+use egui_extras::TableBuilder;
+use std::collections::BTreeMap;
+
+const ROW_HEIGHT: f32 = 36.0;
+const RANGE_WIDTH: f32 = 96.0;
+const TREND_SIZE: egui::Vec2 = egui::vec2(80.0, 28.0);
+
+/// A column of the daily table, the symbol column is always shown first.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, PartialEq, Debug)]
+pub enum Column {
+    Symbol,
+    Shares,
+    Cost,
+    Price,
+    Value,
+    Weight,
+    Change,
+    Pnl,
+    Range,
+    Trend,
+}
+
+impl Column {
+    /// Columns the user can hide and reorder.
+    pub const MOVABLE: [Column; 9] = [
+        Column::Price,
+        Column::Change,
+        Column::Pnl,
+        Column::Shares,
+        Column::Cost,
+        Column::Value,
+        Column::Weight,
+        Column::Range,
+        Column::Trend,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Column::Symbol => "Symbol",
+            Column::Shares => "Shares",
+            Column::Cost => "Cost",
+            Column::Price => "Price",
+            Column::Value => "Value",
+            Column::Weight => "Weight",
+            Column::Change => "Day",
+            Column::Pnl => "P&L",
+            Column::Range => "Range",
+            Column::Trend => "Trend",
+        }
+    }
+}
+
+/// Column order, visibility and sorting of the daily table, kept between sessions.
+#[derive(serde::Deserialize, serde::Serialize, Clone)]
+#[serde(default)]
+pub struct TableLayout {
+    /// Movable columns in display order, with whether each is shown.
+    columns: Vec<(Column, bool)>,
+    /// Sorted column and whether it is ascending, rows keep the data order when unset.
+    sort: Option<(Column, bool)>,
+    /// Closes the trend column draws.
+    trend_length: usize,
+    #[serde(skip)]
+    filter: String,
+}
+
+impl Default for TableLayout {
+    fn default() -> Self {
+        Self {
+            columns: Column::MOVABLE
+                .iter()
+                .map(|column| {
+                    (
+                        *column,
+                        matches!(
+                            column,
+                            Column::Price
+                                | Column::Change
+                                | Column::Pnl
+                                | Column::Range
+                                | Column::Trend
+                        ),
+                    )
+                })
+                .collect(),
+            sort: None,
+            trend_length: 30,
+            filter: String::new(),
+        }
+    }
+}
+
+impl TableLayout {
+    /// Drops unknown or repeated columns and appends hidden ones missing from an older layout.
+    fn normalized(mut self) -> Self {
+        let mut seen = vec![];
+        self.columns.retain(|(column, _)| {
+            let keep = Column::MOVABLE.contains(column) && !seen.contains(column);
+            seen.push(*column);
+            keep
+        });
+        for column in Column::MOVABLE {
+            if !seen.contains(&column) {
+                self.columns.push((column, false));
+            }
+        }
+        self
+    }
+
+    fn visible(&self) -> Vec<Column> {
+        self.columns
+            .iter()
+            .filter(|(_, shown)| *shown)
+            .map(|(column, _)| *column)
+            .collect()
+    }
+
+    /// Sorts by `column`, flipping the direction when it is already the sorted one.
+    fn sort_by(&mut self, column: Column) {
+        self.sort = match self.sort {
+            Some((sorted, ascending)) if sorted == column => Some((column, !ascending)),
+            // numbers read best largest first, names alphabetically
+            _ => Some((column, column == Column::Symbol)),
+        };
+    }
+
+    fn show_menu(&mut self, ui: &mut egui::Ui) {
+        let mut moved = None;
+        let last = self.columns.len().saturating_sub(1);
+        for (index, (column, shown)) in self.columns.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                if ui.add_enabled(index > 0, egui::Button::new("⬆")).clicked() {
+                    moved = Some((index, index - 1));
+                }
+                if ui
+                    .add_enabled(index < last, egui::Button::new("⬇"))
+                    .clicked()
+                {
+                    moved = Some((index, index + 1));
+                }
+                ui.checkbox(shown, column.label());
+            });
+        }
+        if let Some((from, to)) = moved {
+            self.columns.swap(from, to);
+        }
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.label("Trend");
+            ui.add(
+                egui::DragValue::new(&mut self.trend_length)
+                    .clamp_range(5..=250)
+                    .suffix(" days"),
+            );
+        });
+    }
+}
+
 #[derive(serde::Deserialize, serde::Serialize)]
 #[serde(default)]
 pub struct DailyTable {
     name: String,
-    stocks: Vec<Stock>,
+    rows: Vec<Row>,
+    layout: TableLayout,
+    selected: Option<String>,
+    currency: Currency,
+    colors: ColorScheme,
+    format: NumberFormat,
+    #[serde(skip)]
+    scroll_to_selected: bool,
+    /// Height the rows scroll within, unset when a surrounding scroll area holds the table.
+    #[serde(skip)]
+    scroll_height: Option<f32>,
 }
 
 impl Default for DailyTable {
     fn default() -> Self {
-        let stocks = vec![];
-        let name = "default";
         Self {
-            stocks,
-            name: name.to_string(),
+            name: "default".to_string(),
+            rows: vec![],
+            layout: TableLayout::default(),
+            selected: None,
+            currency: Currency::Usd,
+            colors: ColorScheme::RedUp,
+            format: NumberFormat::default(),
+            scroll_to_selected: false,
+            scroll_height: None,
         }
     }
 }
 
 impl DailyTable {
-    pub fn new<S: AsRef<str>, L: AsRef<str>>(name: S, data: &[(L, L, f64, f64)]) -> Self {
-        let mut stocks = vec![];
-        for stock in data {
-            stocks.push(Stock::new(
-                stock.0.as_ref(),
-                stock.1.as_ref(),
-                stock.2,
-                stock.3,
-            ))
-        }
+    pub fn new<S: AsRef<str>>(
+        name: S,
+        holdings: &[data_reader::Stock],
+        layout: TableLayout,
+        currency: Currency,
+        colors: ColorScheme,
+        format: NumberFormat,
+    ) -> Self {
+        let total: f64 = holdings.iter().map(|stock| stock.market_value()).sum();
+        let rows = holdings
+            .iter()
+            .map(|stock| Row::new(stock, total))
+            .collect();
         Self {
             name: name.as_ref().to_string(),
-            stocks,
+            rows,
+            layout: layout.normalized(),
+            selected: None,
+            currency,
+            colors,
+            format,
+            scroll_to_selected: false,
+            scroll_height: None,
+        }
+    }
+
+    pub fn set_scroll_height(&mut self, height: Option<f32>) {
+        self.scroll_height = height;
+    }
+
+    /// Fills in what the rows derive from each symbol's daily bars, oldest first.
+    pub fn set_history(&mut self, history: &BTreeMap<String, Vec<Candle>>) {
+        for row in &mut self.rows {
+            let bars = history
+                .get(&row.symbol)
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            row.year_range = year_range(bars);
+            row.closes = bars.iter().map(|bar| bar.close).collect();
+        }
+    }
+
+    pub fn layout(&self) -> &TableLayout {
+        &self.layout
+    }
+
+    pub fn selected(&self) -> Option<&str> {
+        self.selected.as_deref()
+    }
+
+    /// Selects a row from elsewhere in the app and scrolls it into view.
+    pub fn select(&mut self, symbol: Option<&str>) {
+        self.selected = symbol.map(|symbol| symbol.to_string());
+        self.scroll_to_selected = self.selected.is_some();
+    }
+
+    /// Rows passing the filter, in the sorted order.
+    fn ordered(&self) -> Vec<&Row> {
+        let filter = self.layout.filter.trim().to_lowercase();
+        let mut rows: Vec<_> = self
+            .rows
+            .iter()
+            .filter(|row| {
+                filter.is_empty()
+                    || row.symbol.to_lowercase().contains(&filter)
+                    || row.name.to_lowercase().contains(&filter)
+            })
+            .collect();
+        if let Some((column, ascending)) = self.layout.sort {
+            rows.sort_by(|a, b| {
+                let order = match column {
+                    Column::Symbol => a.symbol.cmp(&b.symbol),
+                    _ => a
+                        .key(column, self.layout.trend_length)
+                        .total_cmp(&b.key(column, self.layout.trend_length)),
+                };
+                if ascending {
+                    order
+                } else {
+                    order.reverse()
+                }
+            });
         }
+        rows
     }
+
     pub fn show(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut self.layout.filter)
+                    .hint_text("Filter")
+                    .desired_width(120.0),
+            );
+            ui.menu_button("Columns", |ui| self.layout.show_menu(ui));
+        });
         ui.separator();
-        for stock in &self.stocks {
-            ui.columns(2, |columns| {
-                columns[0].horizontal(|ui| {
-                    ui.vertical(|ui| {
-                        ui.label(
-                            egui::RichText::new(stock.market_name.clone())
-                                .size(16.0)
-                                .monospace(),
-                        );
-                        ui.label(
-                            egui::RichText::new(stock.index_name.clone())
-                                .size(12.0)
-                                .monospace(),
-                        );
+
+        let columns = self.layout.visible();
+        let mut sort_by = None;
+        let mut clicked = None;
+        let mut scroll_to_selected = std::mem::take(&mut self.scroll_to_selected);
+        let rows = self.ordered();
+        let mut table = TableBuilder::new(ui)
+            .striped(true)
+            .vscroll(self.scroll_height.is_some())
+            .max_scroll_height(self.scroll_height.unwrap_or(f32::INFINITY))
+            .sense(egui::Sense::click())
+            .cell_layout(egui::Layout::right_to_left(Align::Center))
+            .column(egui_extras::Column::auto().at_least(96.0));
+        for _ in &columns {
+            table = table.column(egui_extras::Column::auto().at_least(64.0));
+        }
+        table
+            .header(20.0, |mut header| {
+                for column in std::iter::once(Column::Symbol).chain(columns.iter().copied()) {
+                    header.col(|ui| {
+                        let mut text = column.label().to_string();
+                        match self.layout.sort {
+                            Some((sorted, true)) if sorted == column => text.push_str(" ⬆"),
+                            Some((sorted, false)) if sorted == column => text.push_str(" ⬇"),
+                            _ => {}
+                        }
+                        let label = egui::Label::new(RichText::new(text).size(12.0).monospace())
+                            .sense(egui::Sense::click());
+                        if ui.add(label).clicked() {
+                            sort_by = Some(column);
+                        }
                     });
-                });
-                columns[1].horizontal_wrapped(|ui| {
-                    ui.vertical(|ui| {
-                        ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
-                            ui.label(
-                                egui::RichText::new(stock.index_value.clone())
-                                    .size(16.0)
-                                    .monospace(),
-                            );
-                        });
-                        ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
-                            if stock.index_change > 0. {
-                                let text = format!("+{:.2}%", stock.index_change);
-                                ui.label(
-                                    egui::RichText::new(text)
-                                        .size(12.0)
-                                        .color(egui::Color32::RED)
-                                        .monospace(),
-                                );
-                            } else {
-                                let text = format!("{:.2}%", stock.index_change);
-                                ui.label(
-                                    egui::RichText::new(text)
-                                        .size(12.0)
-                                        .small()
-                                        .color(egui::Color32::LIGHT_BLUE),
-                                );
-                            };
+                }
+            })
+            .body(|mut body| {
+                for row in &rows {
+                    body.row(ROW_HEIGHT, |mut table_row| {
+                        let selected = self.selected.as_deref() == Some(row.symbol.as_str());
+                        table_row.set_selected(selected);
+                        table_row.col(|ui| {
+                            ui.with_layout(egui::Layout::top_down(Align::Min), |ui| {
+                                ui.label(RichText::new(&row.symbol).size(16.0).monospace());
+                                ui.label(RichText::new(&row.name).size(12.0).monospace());
+                            });
                         });
+                        for column in &columns {
+                            table_row.col(|ui| {
+                                row.show_cell(
+                                    ui,
+                                    *column,
+                                    self.currency,
+                                    self.colors,
+                                    self.format,
+                                    self.layout.trend_length,
+                                )
+                            });
+                        }
+                        let response = table_row.response();
+                        if selected && std::mem::take(&mut scroll_to_selected) {
+                            response.scroll_to_me(Some(Align::Center));
+                        }
+                        if response.clicked() {
+                            clicked = Some(row.symbol.clone());
+                        }
                     });
-                });
+                }
             });
-            ui.separator();
+
+        if let Some(column) = sort_by {
+            self.layout.sort_by(column);
+        }
+        if let Some(symbol) = clicked {
+            if self.selected.as_ref() == Some(&symbol) {
+                self.selected = None;
+            } else {
+                self.selected = Some(symbol);
+            }
         }
     }
 }
 
-#[derive(serde::Deserialize, serde::Serialize, Clone)]
+/// One holding as the table shows it.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Default)]
 #[serde(default)]
-pub struct Stock {
-    market_name: String,
-    index_name: String,
-    index_value: String,
-    index_change: f64,
+struct Row {
+    symbol: String,
+    name: String,
+    shares: u32,
+    cost: f64,
+    price: f64,
+    value: f64,
+    weight: f64,
+    change: f64,
+    pnl: f64,
+    pnl_percent: f64,
+    open: f64,
+    high: f64,
+    low: f64,
+    /// Lowest low and highest high of the last 52 weeks, when there is history to take them from.
+    year_range: Option<(f64, f64)>,
+    /// Daily closes oldest first, ending with the current one.
+    closes: Vec<f64>,
 }
 
-impl Default for Stock {
-    fn default() -> Self {
-        Stock::new("NASDAQ", "NASDAQ Composite", 16_920.80, 11.0)
+impl Row {
+    fn new(stock: &data_reader::Stock, total: f64) -> Self {
+        let value = stock.market_value();
+        Self {
+            symbol: stock.symbol.to_string(),
+            name: stock.company_name.to_string(),
+            shares: stock.owned,
+            cost: stock.cost_basis(),
+            price: stock.close_price,
+            value,
+            weight: if total > 0.0 {
+                value / total * 100.0
+            } else {
+                0.0
+            },
+            change: (stock.close_price - stock.yesterday_price) / stock.yesterday_price * 100.0,
+            pnl: stock.unrealized_pnl(),
+            pnl_percent: stock.unrealized_pnl_percent(),
+            open: stock.open_price,
+            high: stock.highest_price,
+            low: stock.lowest_price,
+            year_range: None,
+            closes: vec![],
+        }
+    }
+
+    /// The last `length` closes.
+    fn trend(&self, length: usize) -> &[f64] {
+        &self.closes[self.closes.len().saturating_sub(length)..]
+    }
+
+    /// Value the rows are sorted by, the range sorts by its width relative to the low
+    /// and the trend by its return.
+    fn key(&self, column: Column, trend_length: usize) -> f64 {
+        match column {
+            Column::Symbol => 0.0,
+            Column::Shares => self.shares as f64,
+            Column::Cost => self.cost,
+            Column::Price => self.price,
+            Column::Value => self.value,
+            Column::Weight => self.weight,
+            Column::Change => self.change,
+            Column::Pnl => self.pnl,
+            Column::Range if self.low > 0.0 => (self.high - self.low) / self.low,
+            Column::Range => 0.0,
+            Column::Trend => match self.trend(trend_length) {
+                [first, .., last] if *first != 0.0 => last / first - 1.0,
+                _ => 0.0,
+            },
+        }
+    }
+
+    fn show_cell(
+        &self,
+        ui: &mut egui::Ui,
+        column: Column,
+        currency: Currency,
+        colors: ColorScheme,
+        format: NumberFormat,
+        trend_length: usize,
+    ) {
+        let money = |value: f64| format.money(value, currency);
+        if column == Column::Range {
+            let mut tooltip = format!(
+                "Open {}  High {}  Low {}  Close {}",
+                money(self.open),
+                money(self.high),
+                money(self.low),
+                money(self.price)
+            );
+            if let Some((low, high)) = self.year_range {
+                tooltip += &format!("\n52 weeks {} - {}", money(low), money(high));
+            }
+            self.show_range(ui, colors).on_hover_text(tooltip);
+            return;
+        }
+        if column == Column::Trend {
+            let trend = self.trend(trend_length);
+            if trend.len() > 1 {
+                sparkline(ui, ui.id().with(&self.symbol), trend, TREND_SIZE, colors);
+            }
+            return;
+        }
+        let (top, bottom, tint) = match column {
+            Column::Symbol | Column::Range | Column::Trend => return,
+            Column::Shares => (self.shares.to_string(), None, None),
+            Column::Cost => (money(self.cost), None, None),
+            Column::Price => (money(self.price), None, None),
+            Column::Value => (money(self.value), None, None),
+            Column::Weight => (format!("{:.2}%", self.weight), None, None),
+            Column::Change => (
+                format!("{:+.2}%", self.change),
+                None,
+                Some(colors.of(self.change)),
+            ),
+            Column::Pnl => (
+                format.signed_money(self.pnl, currency),
+                Some(format!("{:+.2}%", self.pnl_percent)),
+                Some(colors.of(self.pnl)),
+            ),
+        };
+        ui.with_layout(egui::Layout::top_down(Align::Max), |ui| {
+            let mut text = RichText::new(top).size(14.0).monospace();
+            if let Some(tint) = tint {
+                text = text.color(tint);
+            }
+            ui.label(text);
+            if let Some(bottom) = bottom {
+                let mut text = RichText::new(bottom).size(12.0).monospace();
+                if let Some(tint) = tint {
+                    text = text.color(tint);
+                }
+                ui.label(text);
+            }
+        });
+    }
+
+    /// The day's low to high with the open as a tick and the close as a dot, the body between
+    /// them coloured like a candle. The 52-week range goes underneath with the day shaded in.
+    fn show_range(&self, ui: &mut egui::Ui, colors: ColorScheme) -> egui::Response {
+        let height = if self.year_range.is_some() {
+            24.0
+        } else {
+            12.0
+        };
+        let (rect, response) =
+            ui.allocate_exact_size(egui::vec2(RANGE_WIDTH, height), egui::Sense::hover());
+        let painter = ui.painter_at(rect.expand(4.0));
+        let track = ui.visuals().widgets.inactive.bg_fill;
+        let ink = ui.visuals().text_color();
+        let color = if self.price >= self.open {
+            colors.up()
+        } else {
+            colors.down()
+        };
+        let x = |low: f64, high: f64, value: f64| {
+            rect.left() + RANGE_WIDTH * position(low, high, value)
+        };
+        let bar = |y: f32, from: f32, to: f32| {
+            egui::Rect::from_min_max(
+                egui::pos2(from, y - 2.0),
+                egui::pos2(to.max(from + 1.0), y + 2.0),
+            )
+        };
+
+        let y = rect.top() + 6.0;
+        painter.rect_filled(bar(y, rect.left(), rect.right()), 2.0, track);
+        let open = x(self.low, self.high, self.open);
+        let close = x(self.low, self.high, self.price);
+        painter.rect_filled(bar(y, open.min(close), open.max(close)), 0.0, color);
+        painter.line_segment(
+            [egui::pos2(open, y - 5.0), egui::pos2(open, y + 5.0)],
+            Stroke::new(1.5, ink),
+        );
+        painter.circle_filled(egui::pos2(close, y), 3.0, color);
+
+        if let Some((low, high)) = self.year_range {
+            let y = rect.top() + 18.0;
+            painter.rect_filled(bar(y, rect.left(), rect.right()), 2.0, track);
+            let day = bar(y, x(low, high, self.low), x(low, high, self.high));
+            painter.rect_filled(day, 0.0, ui.visuals().widgets.hovered.bg_fill);
+            painter.circle_filled(egui::pos2(x(low, high, self.price), y), 3.0, ink);
+        }
+        response
     }
 }
 
-impl Stock {
-    pub fn new<S: AsRef<str>>(
-        market_name: S,
-        index_name: S,
-        index_value: f64,
-        index_change: f64,
-    ) -> Self {
-        Self {
-            market_name: market_name.as_ref().to_string(),
-            index_name: index_name.as_ref().to_string(),
-            index_value: index_value.to_string(),
-            index_change,
+/// Where `value` sits between `low` and `high`, from 0 to 1.
+fn position(low: f64, high: f64, value: f64) -> f32 {
+    if high > low {
+        ((value - low) / (high - low)).clamp(0.0, 1.0) as f32
+    } else {
+        0.5
+    }
+}
+
+/// Lowest low and highest high of the year up to the last bar, none without earlier bars.
+fn year_range(bars: &[Candle]) -> Option<(f64, f64)> {
+    let parse = |bar: &Candle| NaiveDate::parse_from_str(&bar.date, "%Y-%m-%d").ok();
+    let latest = parse(bars.last()?)?;
+    let start = latest.checked_sub_months(Months::new(12))?;
+    let year: Vec<_> = bars
+        .iter()
+        .filter(|bar| parse(bar).is_some_and(|date| date > start))
+        .collect();
+    if year.len() < 2 {
+        return None;
+    }
+    let low = year.iter().map(|bar| bar.low).fold(f64::INFINITY, f64::min);
+    let high = year
+        .iter()
+        .map(|bar| bar.high)
+        .fold(f64::NEG_INFINITY, f64::max);
+    Some((low, high))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn year_range_skips_older_bars() {
+        let bar = |date: &str, low: f64, high: f64| Candle {
+            date: date.to_string(),
+            open: low,
+            high,
+            low,
+            close: high,
+        };
+        let bars = vec![
+            bar("2023-06-01", 1.0, 50.0),
+            bar("2023-06-20", 8.0, 12.0),
+            bar("2024-01-10", 5.0, 9.0),
+            bar("2024-06-14", 10.0, 11.0),
+        ];
+        assert_eq!(year_range(&bars), Some((5.0, 12.0)));
+        assert_eq!(year_range(&bars[3..]), None);
+    }
+
+    #[test]
+    fn older_layouts_gain_missing_columns() {
+        let layout = TableLayout {
+            columns: vec![
+                (Column::Pnl, true),
+                (Column::Symbol, true),
+                (Column::Pnl, false),
+            ],
+            ..TableLayout::default()
         }
+        .normalized();
+        assert_eq!(layout.columns.len(), Column::MOVABLE.len());
+        assert_eq!(layout.columns[0], (Column::Pnl, true));
+        assert_eq!(layout.visible(), vec![Column::Pnl]);
     }
 }
@@ -0,0 +1,158 @@
+use std::fmt;
+
+/// Everything that can go wrong between requesting the portfolio and having a usable `PortfolioRawData`.
+#[derive(Debug, Clone)]
+pub enum DataError {
+    /// The request or read itself failed.
+    Io(String),
+    /// The payload is not valid utf-8 or not valid json.
+    Decode {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    /// The json is valid but does not match the expected shape.
+    Schema {
+        path: String,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    /// The shape is right but a value makes no sense.
+    Value { path: String, message: String },
+}
+
+impl DataError {
+    pub fn from_utf8(bytes: &[u8], err: std::str::Utf8Error) -> Self {
+        let (line, column) = line_column(&bytes[..err.valid_up_to()]);
+        DataError::Decode {
+            line,
+            column,
+            message: err.to_string(),
+        }
+    }
+
+    pub fn from_json(err: serde_path_to_error::Error<serde_json::Error>) -> Self {
+        let path = err.path().to_string();
+        let inner = err.into_inner();
+        // serde_json appends the position to its message, it is reported separately here
+        let message = inner.to_string();
+        let suffix = format!(" at line {} column {}", inner.line(), inner.column());
+        let message = message
+            .strip_suffix(&suffix)
+            .unwrap_or(&message)
+            .to_string();
+        match inner.classify() {
+            serde_json::error::Category::Io => DataError::Io(message),
+            serde_json::error::Category::Syntax | serde_json::error::Category::Eof => {
+                DataError::Decode {
+                    line: inner.line(),
+                    column: inner.column(),
+                    message,
+                }
+            }
+            serde_json::error::Category::Data => DataError::Schema {
+                path,
+                line: inner.line(),
+                column: inner.column(),
+                message,
+            },
+        }
+    }
+
+    pub fn value<S: AsRef<str>, M: AsRef<str>>(path: S, message: M) -> Self {
+        DataError::Value {
+            path: path.as_ref().to_string(),
+            message: message.as_ref().to_string(),
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            DataError::Io(_) => "Could not load portfolio data",
+            DataError::Decode { .. } => "Portfolio data is not valid json",
+            DataError::Schema { .. } => "Portfolio data has an unexpected shape",
+            DataError::Value { .. } => "Portfolio data contains an invalid value",
+        }
+    }
+
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            DataError::Schema { path, .. } | DataError::Value { path, .. } => Some(path),
+            _ => None,
+        }
+    }
+
+    pub fn position(&self) -> Option<(usize, usize)> {
+        match self {
+            DataError::Decode { line, column, .. } | DataError::Schema { line, column, .. } => {
+                Some((*line, *column))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            DataError::Io(message)
+            | DataError::Decode { message, .. }
+            | DataError::Schema { message, .. }
+            | DataError::Value { message, .. } => message,
+        }
+    }
+}
+
+impl fmt::Display for DataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.title(), self.message())?;
+        if let Some(path) = self.path() {
+            write!(f, " at `{}`", path)?;
+        }
+        if let Some((line, column)) = self.position() {
+            write!(f, " (line {}, column {})", line, column)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for DataError {}
+
+fn line_column(bytes: &[u8]) -> (usize, usize) {
+    let line = bytes.iter().filter(|&&b| b == b'\n').count() + 1;
+    let column = bytes.iter().rev().take_while(|&&b| b != b'\n').count() + 1;
+    (line, column)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::modals::data_reader::PortfolioRawData;
+
+    #[test]
+    fn schema_errors_report_path_and_position() {
+        let json = b"{\n  \"holdings\": [\n    {\"symbol\": 5}\n  ],\n  \"history\": []\n}";
+        let err = PortfolioRawData::from_slice(json).unwrap_err();
+        assert_eq!(err.title(), "Portfolio data has an unexpected shape");
+        assert_eq!(err.path(), Some("holdings[0].symbol"));
+        assert_eq!(err.position(), Some((3, 16)));
+        assert!(!err.message().contains("line"));
+    }
+
+    #[test]
+    fn syntax_and_truncation_are_decode_errors() {
+        let err = PortfolioRawData::from_slice(b"{\"holdings\": x}").unwrap_err();
+        assert_eq!(err.title(), "Portfolio data is not valid json");
+        assert_eq!(err.position(), Some((1, 14)));
+        assert_eq!(err.path(), None);
+
+        let err = PortfolioRawData::from_slice(b"{\"holdings\": [\n").unwrap_err();
+        assert_eq!(err.title(), "Portfolio data is not valid json");
+        assert_eq!(err.position(), Some((2, 0)));
+    }
+
+    #[test]
+    fn invalid_utf8_points_at_the_bad_byte() {
+        let err = PortfolioRawData::from_slice(b"{\n  \"a\": \"\xff\"}").unwrap_err();
+        assert_eq!(err.title(), "Portfolio data is not valid json");
+        assert_eq!(err.position(), Some((2, 9)));
+    }
+}
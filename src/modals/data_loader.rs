@@ -0,0 +1,49 @@
+use crate::modals::data_error::DataError;
+use crate::modals::data_reader::PortfolioRawData;
+use std::sync::{Arc, Mutex};
+
+const PORTFOLIO_URL: &str = env!("PORTFOLIO_URL");
+const SUPABASE_TOKEN: Option<&str> = option_env!("SUPABASE_TOKEN");
+
+type LoadResult = Result<PortfolioRawData, DataError>;
+
+/// Fetches the portfolio json in the background, works on both native and wasm32.
+pub struct DataLoader {
+    result: Arc<Mutex<Option<LoadResult>>>,
+}
+
+impl DataLoader {
+    pub fn fetch(ctx: &egui::Context) -> Self {
+        let result = Arc::new(Mutex::new(None));
+
+        let mut request = ehttp::Request::get(PORTFOLIO_URL);
+        if let Some(token) = SUPABASE_TOKEN {
+            request.headers.insert("apikey", token);
+            request
+                .headers
+                .insert("Authorization", format!("Bearer {}", token));
+        }
+
+        let sender = result.clone();
+        let ctx = ctx.clone();
+        ehttp::fetch(request, move |response| {
+            let loaded = match response {
+                Ok(response) if response.ok => PortfolioRawData::from_slice(&response.bytes),
+                Ok(response) => Err(DataError::Io(format!(
+                    "{} {}",
+                    response.status, response.status_text
+                ))),
+                Err(err) => Err(DataError::Io(err)),
+            };
+            *sender.lock().unwrap() = Some(loaded);
+            ctx.request_repaint();
+        });
+
+        Self { result }
+    }
+
+    /// Takes the result once the request has finished.
+    pub fn poll(&self) -> Option<LoadResult> {
+        self.result.lock().unwrap().take()
+    }
+}
@@ -1,6 +1,12 @@
+use crate::modals::benchmark::IndexClose;
+use crate::modals::calendar::trading_day;
+use crate::modals::candle_chart::Candle;
+use crate::modals::data_error::DataError;
+use crate::modals::ledger::Transaction;
+use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Stock {
     pub symbol: String,
     pub owned: u32,
@@ -11,6 +17,69 @@ pub struct Stock {
     pub open_price: f64,
     pub lowest_price: f64,
     pub yesterday_price: f64,
+    #[serde(default)]
+    pub update_at: String,
+    /// Classification, also filled in from `assets/classification.csv` when missing here.
+    #[serde(default)]
+    pub sector: Option<String>,
+    #[serde(default)]
+    pub industry: Option<String>,
+    #[serde(default)]
+    pub country: Option<String>,
+    #[serde(default)]
+    pub asset_class: Option<String>,
+    #[serde(default)]
+    pub account: Option<String>,
+}
+
+impl Stock {
+    /// The latest bar, taken from the holding itself and dated on the session it closed in.
+    pub fn candle(&self) -> Candle {
+        let date = NaiveDate::parse_from_str(&self.update_at, "%Y-%m-%d")
+            .map(|date| trading_day(date).format("%Y-%m-%d").to_string())
+            .unwrap_or_else(|_| self.update_at.clone());
+        Candle {
+            date,
+            open: self.open_price,
+            high: self.highest_price,
+            low: self.lowest_price,
+            close: self.close_price,
+        }
+    }
+
+    /// Same holding with every price multiplied by `factor`, used for currency conversion.
+    pub fn scaled(&self, factor: f64) -> Stock {
+        Stock {
+            cost: self.cost * factor,
+            close_price: self.close_price * factor,
+            highest_price: self.highest_price * factor,
+            open_price: self.open_price * factor,
+            lowest_price: self.lowest_price * factor,
+            yesterday_price: self.yesterday_price * factor,
+            ..self.clone()
+        }
+    }
+
+    pub fn market_value(&self) -> f64 {
+        self.owned as f64 * self.close_price
+    }
+
+    pub fn cost_basis(&self) -> f64 {
+        self.owned as f64 * self.cost
+    }
+
+    pub fn unrealized_pnl(&self) -> f64 {
+        self.market_value() - self.cost_basis()
+    }
+
+    pub fn unrealized_pnl_percent(&self) -> f64 {
+        let cost_basis = self.cost_basis();
+        if cost_basis == 0.0 {
+            0.0
+        } else {
+            self.unrealized_pnl() / cost_basis * 100.0
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -19,26 +88,258 @@ pub struct Record {
     pub balance: f64,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// One daily bar of a single symbol.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Price {
+    pub symbol: String,
+    pub date: String,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    #[serde(default)]
+    pub volume: f64,
+}
+
+impl Price {
+    pub fn scaled(&self, factor: f64) -> Price {
+        Price {
+            open: self.open * factor,
+            high: self.high * factor,
+            low: self.low * factor,
+            close: self.close * factor,
+            ..self.clone()
+        }
+    }
+
+    pub fn candle(&self) -> Candle {
+        Candle {
+            date: self.date.clone(),
+            open: self.open,
+            high: self.high,
+            low: self.low,
+            close: self.close,
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
 pub struct PortfolioRawData {
     pub holdings: Vec<Stock>,
     pub history: Vec<Record>,
+    /// Per-symbol daily bars, missing in data produced before it was added.
+    #[serde(default)]
+    pub prices: Vec<Price>,
+    /// Ledger entries, when present holdings are replayed from them.
+    #[serde(default)]
+    pub transactions: Vec<Transaction>,
+    /// Index closes to compare against, on top of the bundled ones.
+    #[serde(default)]
+    pub benchmarks: Vec<IndexClose>,
+    /// Records left out because of an invalid value.
+    #[serde(skip)]
+    pub skipped: Vec<DataError>,
 }
 
-impl Default for PortfolioRawData {
-    fn default() -> Self {
+impl PortfolioRawData {
+    /// Sample portfolio shown before login.
+    pub fn dummy() -> Result<Self, DataError> {
         let json_file: &[u8] = include_bytes!("../../assets/dummy_data.json");
-        let data_str = std::str::from_utf8(json_file).unwrap();
+        PortfolioRawData::from_slice(json_file)
+    }
+
+    /// Data compiled into the binary by `build.rs`, used when the runtime fetch fails.
+    pub fn embedded() -> Option<Result<Self, DataError>> {
+        #[cfg(feature = "embed_data")]
+        {
+            let json_file: &[u8] = include_bytes!("../../assets/data.json");
+            Some(PortfolioRawData::from_slice(json_file))
+        }
+        #[cfg(not(feature = "embed_data"))]
+        None
+    }
 
-        serde_json::from_str(data_str).unwrap()
+    /// Daily bars of `symbol`, oldest first.
+    pub fn price_history(&self, symbol: &str) -> Vec<&Price> {
+        let mut prices: Vec<_> = self.prices.iter().filter(|p| p.symbol == symbol).collect();
+        prices.sort_by(|a, b| a.date.cmp(&b.date));
+        prices
+    }
+
+    pub fn from_slice(json_file: &[u8]) -> Result<Self, DataError> {
+        let data_str =
+            std::str::from_utf8(json_file).map_err(|err| DataError::from_utf8(json_file, err))?;
+
+        let deserializer = &mut serde_json::Deserializer::from_str(data_str);
+        let mut data: PortfolioRawData =
+            serde_path_to_error::deserialize(deserializer).map_err(DataError::from_json)?;
+        data.validate();
+        Ok(data)
+    }
+
+    /// Drops every record with an invalid value, the reason of each is kept in `skipped`.
+    fn validate(&mut self) {
+        let mut skipped = vec![];
+        retain_valid(&mut self.holdings, &mut skipped, check_stock);
+        retain_valid(&mut self.history, &mut skipped, check_record);
+        retain_valid(&mut self.prices, &mut skipped, check_price);
+        retain_valid(&mut self.transactions, &mut skipped, check_transaction);
+        retain_valid(&mut self.benchmarks, &mut skipped, check_index_close);
+        self.skipped = skipped;
     }
 }
 
-impl PortfolioRawData {
-    pub fn new() -> Self {
-        let json_file: &[u8] = include_bytes!("../../assets/data.json");
-        let data_str = std::str::from_utf8(json_file).unwrap();
+/// Keeps the items `check` accepts, moving the error of each rejected one to `skipped`.
+fn retain_valid<T>(
+    items: &mut Vec<T>,
+    skipped: &mut Vec<DataError>,
+    check: fn(usize, &T) -> Result<(), DataError>,
+) {
+    let mut index = 0;
+    items.retain(|item| {
+        let result = check(index, item);
+        index += 1;
+        match result {
+            Ok(()) => true,
+            Err(err) => {
+                log::warn!("skipping record: {}", err);
+                skipped.push(err);
+                false
+            }
+        }
+    });
+}
+
+fn check_stock(i: usize, stock: &Stock) -> Result<(), DataError> {
+    if stock.symbol.trim().is_empty() {
+        return Err(DataError::value(
+            format!("holdings[{}].symbol", i),
+            "symbol is empty",
+        ));
+    }
+    let prices = [
+        ("cost", stock.cost),
+        ("close_price", stock.close_price),
+        ("highest_price", stock.highest_price),
+        ("open_price", stock.open_price),
+        ("lowest_price", stock.lowest_price),
+        ("yesterday_price", stock.yesterday_price),
+    ];
+    for (field, value) in prices {
+        if !value.is_finite() || value < 0.0 {
+            return Err(DataError::value(
+                format!("holdings[{}].{}", i, field),
+                format!("{} is not a valid price", value),
+            ));
+        }
+    }
+    if stock.yesterday_price == 0.0 {
+        return Err(DataError::value(
+            format!("holdings[{}].yesterday_price", i),
+            "previous close must not be zero",
+        ));
+    }
+    Ok(())
+}
+
+fn check_record(i: usize, record: &Record) -> Result<(), DataError> {
+    if NaiveDate::parse_from_str(&record.date, "%Y-%m-%d").is_err() {
+        return Err(DataError::value(
+            format!("history[{}].date", i),
+            format!("`{}` is not a YYYY-MM-DD date", record.date),
+        ));
+    }
+    if !record.balance.is_finite() {
+        return Err(DataError::value(
+            format!("history[{}].balance", i),
+            format!("{} is not a valid balance", record.balance),
+        ));
+    }
+    Ok(())
+}
+
+fn check_price(i: usize, price: &Price) -> Result<(), DataError> {
+    if NaiveDate::parse_from_str(&price.date, "%Y-%m-%d").is_err() {
+        return Err(DataError::value(
+            format!("prices[{}].date", i),
+            format!("`{}` is not a YYYY-MM-DD date", price.date),
+        ));
+    }
+    let values = [
+        ("open", price.open),
+        ("high", price.high),
+        ("low", price.low),
+        ("close", price.close),
+        ("volume", price.volume),
+    ];
+    for (field, value) in values {
+        if !value.is_finite() || value < 0.0 {
+            return Err(DataError::value(
+                format!("prices[{}].{}", i, field),
+                format!("{} is not a valid value", value),
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn check_transaction(i: usize, transaction: &Transaction) -> Result<(), DataError> {
+    transaction
+        .validate()
+        .map_err(|err| DataError::value(format!("transactions[{}]", i), err))
+}
+
+fn check_index_close(i: usize, close: &IndexClose) -> Result<(), DataError> {
+    if NaiveDate::parse_from_str(&close.date, "%Y-%m-%d").is_err() {
+        return Err(DataError::value(
+            format!("benchmarks[{}].date", i),
+            format!("`{}` is not a YYYY-MM-DD date", close.date),
+        ));
+    }
+    if !close.close.is_finite() || close.close <= 0.0 {
+        return Err(DataError::value(
+            format!("benchmarks[{}].close", i),
+            format!("{} is not a valid close", close.close),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        serde_json::from_str(data_str).unwrap()
+    #[test]
+    fn invalid_records_are_skipped() {
+        let json = br#"{
+            "holdings": [
+                {"symbol": "AAA", "owned": 1, "cost": 1.0, "company_name": "A", "close_price": 2.0,
+                 "highest_price": 2.0, "open_price": 2.0, "lowest_price": 2.0, "yesterday_price": 0.0},
+                {"symbol": "BBB", "owned": 1, "cost": 1.0, "company_name": "B", "close_price": 2.0,
+                 "highest_price": 2.0, "open_price": 2.0, "lowest_price": 2.0, "yesterday_price": 1.0}
+            ],
+            "history": [{"date": "2024-13-01", "balance": 1.0}, {"date": "2024-05-01", "balance": 2.0}],
+            "transactions": [{"date": "2024-05-01", "symbol": "BBB", "kind": "Buy", "shares": 0}],
+            "benchmarks": [
+                {"symbol": "X", "date": "2024-05-01", "close": 0.0},
+                {"symbol": "X", "date": "2024-05-02", "close": 1.0}
+            ]
+        }"#;
+        let data = PortfolioRawData::from_slice(json).unwrap();
+        assert_eq!(data.holdings.len(), 1);
+        assert_eq!(data.holdings[0].symbol, "BBB");
+        assert_eq!(data.history.len(), 1);
+        let paths: Vec<_> = data.skipped.iter().filter_map(|err| err.path()).collect();
+        assert_eq!(
+            paths,
+            [
+                "holdings[0].yesterday_price",
+                "history[0].date",
+                "transactions[0]",
+                "benchmarks[0].close"
+            ]
+        );
+        assert!(data.transactions.is_empty());
+        assert_eq!(data.benchmarks.len(), 1);
     }
 }
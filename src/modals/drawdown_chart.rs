@@ -0,0 +1,120 @@
+use crate::modals::calendar::{date_to_day, day_to_date};
+use crate::modals::colors::ColorScheme;
+use crate::modals::data_reader::Record;
+use crate::modals::ranged_history::RangedHistory;
+use crate::modals::risk::{max_drawdown, underwater, wealth_index, Drawdown};
+use chrono::NaiveDate;
+use eframe::emath::Vec2;
+use egui_plot::{Legend, Line, MarkerShape, Plot, Points, VLine};
+
+/// Percentage below the running high over the range picked in the period chart.
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct DrawdownChart {
+    name: String,
+    history: RangedHistory,
+    colors: ColorScheme,
+    #[serde(skip)]
+    depths: Vec<(NaiveDate, f64)>,
+    #[serde(skip)]
+    deepest: Option<Drawdown>,
+}
+
+impl Default for DrawdownChart {
+    fn default() -> Self {
+        DrawdownChart::new("default", vec![], vec![], ColorScheme::RedUp)
+    }
+}
+
+impl DrawdownChart {
+    /// Cash flows are taken out first, so a withdrawal does not show up as a drawdown.
+    pub fn new<S: AsRef<str>>(
+        name: S,
+        data: Vec<Record>,
+        flows: Vec<Record>,
+        colors: ColorScheme,
+    ) -> Self {
+        let mut chart = Self {
+            name: name.as_ref().to_string(),
+            history: RangedHistory::new(data, flows),
+            colors,
+            depths: vec![],
+            deepest: None,
+        };
+        chart.compute();
+        chart
+    }
+
+    pub fn set_range(&mut self, range: Option<(NaiveDate, NaiveDate)>) {
+        if self.history.set_range(range) {
+            self.compute();
+        }
+    }
+
+    fn compute(&mut self) {
+        let (balances, flows) = self.history.parsed();
+        let index = wealth_index(&balances, &flows);
+        self.depths = underwater(&index);
+        self.deepest = max_drawdown(&index);
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui) {
+        Plot::new(self.name.clone())
+            .legend(Legend::default())
+            .show_grid(false)
+            .show_background(false)
+            .set_margin_fraction(Vec2 { x: 0.05, y: 0.1 })
+            .include_y(0.0)
+            .y_axis_formatter(|value, _, _| format!("{:.0}%", value.value * 100.0))
+            .x_axis_formatter(|value, _, _| {
+                if value.value.fract() != 0.0 {
+                    return "".to_string();
+                }
+                day_to_date(value.value)
+                    .map(|date| date.format("%Y-%m-%d").to_string())
+                    .unwrap_or_default()
+            })
+            .label_formatter(|name, value| {
+                if name.is_empty() {
+                    return "".to_string();
+                }
+                let date = day_to_date(value.x.round())
+                    .map(|date| date.format("%Y-%m-%d").to_string())
+                    .unwrap_or_default();
+                format!("{}\n{:.2}%", date, value.y * 100.0)
+            })
+            .view_aspect(2.4)
+            .show(ui, |plot_ui| {
+                let points: Vec<_> = self
+                    .depths
+                    .iter()
+                    .map(|(date, depth)| [date_to_day(*date), *depth])
+                    .collect();
+                let line = Line::new(points)
+                    .name("Drawdown")
+                    .color(self.colors.down())
+                    .fill(0.0);
+                plot_ui.line(line);
+
+                if let Some(deepest) = self.deepest {
+                    let trough = Points::new([date_to_day(deepest.trough), deepest.depth])
+                        .name(format!(
+                            "Max {:.2}% on {}",
+                            deepest.depth * 100.0,
+                            deepest.trough
+                        ))
+                        .shape(MarkerShape::Down)
+                        .filled(true)
+                        .radius(5.0)
+                        .color(self.colors.down());
+                    plot_ui.points(trough);
+                    if let Some(recovery) = deepest.recovery {
+                        let line = VLine::new(date_to_day(recovery))
+                            .name(format!("Recovered on {}", recovery))
+                            .color(self.colors.up());
+                        plot_ui.vline(line);
+                    }
+                }
+            });
+    }
+}
@@ -0,0 +1,186 @@
+use crate::modals::currency::Currency;
+
+/// Unit steps of compact amounts, largest first, as (size, unit, unit in latin letters).
+const ENGLISH_UNITS: [(f64, &str, &str); 4] = [
+    (1e12, "T", "T"),
+    (1e9, "B", "B"),
+    (1e6, "M", "M"),
+    (1e3, "k", "k"),
+];
+const KOREAN_UNITS: [(f64, &str, &str); 3] =
+    [(1e12, "조", "jo"), (1e8, "억", "eok"), (1e4, "만", "man")];
+
+/// Which units large amounts are abbreviated with.
+#[derive(Debug, serde::Deserialize, serde::Serialize, Clone, Copy, PartialEq)]
+pub enum Locale {
+    English,
+    Korean,
+}
+
+impl Locale {
+    pub const ALL: [Locale; 2] = [Locale::English, Locale::Korean];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Locale::English => "English (k, M, B)",
+            Locale::Korean => "Korean (man, eok, jo)",
+        }
+    }
+
+    fn units(&self) -> &'static [(f64, &'static str, &'static str)] {
+        match self {
+            Locale::English => &ENGLISH_UNITS,
+            Locale::Korean => &KOREAN_UNITS,
+        }
+    }
+}
+
+/// How amounts are written across the app.
+#[derive(Debug, serde::Deserialize, serde::Serialize, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct NumberFormat {
+    pub locale: Locale,
+    /// Write `$1,234` rather than `1,234$`.
+    pub symbol_first: bool,
+    /// Digits after the decimal point, the currency's own when unset.
+    pub decimals: Option<usize>,
+    /// Whether the fonts can draw 만, 억 and 조, latin spellings are used otherwise.
+    #[serde(skip)]
+    hangul: bool,
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        Self {
+            locale: Locale::English,
+            symbol_first: false,
+            decimals: None,
+            hangul: false,
+        }
+    }
+}
+
+impl NumberFormat {
+    /// Returns true when the flag changed and amounts need to be written again.
+    pub fn set_hangul(&mut self, hangul: bool) -> bool {
+        std::mem::replace(&mut self.hangul, hangul) != hangul
+    }
+
+    fn decimals(&self, currency: Currency) -> usize {
+        self.decimals.unwrap_or(currency.decimals())
+    }
+
+    /// Puts the sign and `symbol` around `digits`, a value that rounds to zero gets no sign.
+    fn place(&self, value: f64, digits: String, symbol: &str) -> String {
+        let sign = if value < 0.0 && digits.bytes().any(|b| b.is_ascii_digit() && b != b'0') {
+            "-"
+        } else {
+            ""
+        };
+        if self.symbol_first {
+            format!("{}{}{}", sign, symbol, digits)
+        } else {
+            format!("{}{}{}", sign, digits, symbol)
+        }
+    }
+
+    /// `value` with thousands separators and no symbol, like `-1,234.50`.
+    pub fn amount(&self, value: f64, currency: Currency) -> String {
+        let digits = group(value.abs(), self.decimals(currency));
+        self.place(value, digits, "")
+    }
+
+    /// `value` in full with its currency symbol, like `$1,234.50`.
+    pub fn money(&self, value: f64, currency: Currency) -> String {
+        let digits = group(value.abs(), self.decimals(currency));
+        self.place(value, digits, currency.symbol())
+    }
+
+    /// Same as [`NumberFormat::money`] with a `+` in front of gains.
+    pub fn signed_money(&self, value: f64, currency: Currency) -> String {
+        let text = self.money(value, currency);
+        if value > 0.0 {
+            format!("+{}", text)
+        } else {
+            text
+        }
+    }
+
+    /// `value` shortened to the largest unit it reaches, like `$1.23M` or `₩1.23억`.
+    pub fn compact(&self, value: f64, currency: Currency) -> String {
+        let unit = self
+            .locale
+            .units()
+            .iter()
+            .find(|(size, _, _)| value.abs() >= *size);
+        match unit {
+            Some((size, unit, latin)) => {
+                let unit = if self.hangul { unit } else { latin };
+                let digits = format!("{}{}", group(value.abs() / size, 2), unit);
+                self.place(value, digits, currency.symbol())
+            }
+            None => self.money(value, currency),
+        }
+    }
+
+    /// Currency symbol to put on an input field, as (prefix, suffix).
+    pub fn affixes(&self, currency: Currency) -> (&'static str, &'static str) {
+        if self.symbol_first {
+            (currency.symbol(), "")
+        } else {
+            ("", currency.symbol())
+        }
+    }
+}
+
+/// Non-negative `value` rounded to `decimals` with a comma between each group of three digits.
+fn group(value: f64, decimals: usize) -> String {
+    let text = format!("{:.*}", decimals, value);
+    let (whole, fraction) = match text.split_once('.') {
+        Some((whole, fraction)) => (whole, Some(fraction)),
+        None => (text.as_str(), None),
+    };
+    let mut grouped = String::new();
+    for (index, digit) in whole.chars().enumerate() {
+        if index > 0 && (whole.len() - index) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    if let Some(fraction) = fraction {
+        grouped.push('.');
+        grouped.push_str(fraction);
+    }
+    grouped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn groups_thousands() {
+        assert_eq!(group(0.0, 2), "0.00");
+        assert_eq!(group(999.995, 2), "1,000.00");
+        assert_eq!(group(1234567.0, 0), "1,234,567");
+        let format = NumberFormat {
+            symbol_first: true,
+            ..NumberFormat::default()
+        };
+        assert_eq!(format.money(-1234.5, Currency::Usd), "-$1,234.50");
+        assert_eq!(format.signed_money(12.0, Currency::Krw), "+₩12");
+        assert_eq!(format.money(-0.001, Currency::Usd), "$0.00");
+    }
+
+    #[test]
+    fn korean_units_fall_back_to_latin() {
+        let mut format = NumberFormat {
+            locale: Locale::Korean,
+            ..NumberFormat::default()
+        };
+        assert_eq!(format.compact(123_000_000.0, Currency::Krw), "1.23eok₩");
+        assert_eq!(format.compact(9_999.0, Currency::Krw), "9,999₩");
+        assert!(format.set_hangul(true));
+        assert_eq!(format.compact(-45_600.0, Currency::Krw), "-4.56만₩");
+    }
+}
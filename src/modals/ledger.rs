@@ -0,0 +1,535 @@
+use crate::modals::data_error::DataError;
+use crate::modals::data_reader::{Record, Stock};
+use chrono::NaiveDate;
+use std::collections::BTreeMap;
+
+#[derive(Debug, serde::Deserialize, serde::Serialize, Clone, Copy, PartialEq)]
+pub enum TransactionKind {
+    Buy,
+    Sell,
+    Dividend,
+    Fee,
+}
+
+impl TransactionKind {
+    pub const ALL: [TransactionKind; 4] = [
+        TransactionKind::Buy,
+        TransactionKind::Sell,
+        TransactionKind::Dividend,
+        TransactionKind::Fee,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            TransactionKind::Buy => "Buy",
+            TransactionKind::Sell => "Sell",
+            TransactionKind::Dividend => "Dividend",
+            TransactionKind::Fee => "Fee",
+        }
+    }
+}
+
+/// How the cost of sold shares is picked from the open lots.
+#[derive(Debug, serde::Deserialize, serde::Serialize, Clone, Copy, PartialEq)]
+pub enum CostBasis {
+    Average,
+    Fifo,
+    Lifo,
+    SpecificLot,
+}
+
+impl CostBasis {
+    pub const ALL: [CostBasis; 4] = [
+        CostBasis::Average,
+        CostBasis::Fifo,
+        CostBasis::Lifo,
+        CostBasis::SpecificLot,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            CostBasis::Average => "Average",
+            CostBasis::Fifo => "FIFO",
+            CostBasis::Lifo => "LIFO",
+            CostBasis::SpecificLot => "Specific lot",
+        }
+    }
+}
+
+/// A single ledger entry.
+///
+/// `shares` and `price` describe the trade for buys and sells, `fee` is the commission paid.
+/// A dividend puts the received cash in `price`, a standalone fee only uses `fee`.
+/// `lot` is the id of the lot a sell closes, used by `CostBasis::SpecificLot`.
+#[derive(Debug, serde::Deserialize, serde::Serialize, Clone)]
+pub struct Transaction {
+    pub date: String,
+    pub symbol: String,
+    pub kind: TransactionKind,
+    #[serde(default)]
+    pub shares: u32,
+    #[serde(default)]
+    pub price: f64,
+    #[serde(default)]
+    pub fee: f64,
+    #[serde(default)]
+    pub lot: Option<String>,
+}
+
+impl Default for Transaction {
+    fn default() -> Self {
+        Self {
+            date: chrono::Local::now().date_naive().to_string(),
+            symbol: "".to_string(),
+            kind: TransactionKind::Buy,
+            shares: 0,
+            price: 0.0,
+            fee: 0.0,
+            lot: None,
+        }
+    }
+}
+
+impl Transaction {
+    pub fn validate(&self) -> Result<(), String> {
+        if NaiveDate::parse_from_str(&self.date, "%Y-%m-%d").is_err() {
+            return Err(format!("`{}` is not a YYYY-MM-DD date", self.date));
+        }
+        if self.symbol.trim().is_empty() {
+            return Err("symbol is empty".to_string());
+        }
+        if !self.price.is_finite() || self.price < 0.0 {
+            return Err(format!("{} is not a valid price", self.price));
+        }
+        if !self.fee.is_finite() || self.fee < 0.0 {
+            return Err(format!("{} is not a valid fee", self.fee));
+        }
+        let needs_shares = matches!(self.kind, TransactionKind::Buy | TransactionKind::Sell);
+        if needs_shares && self.shares == 0 {
+            return Err(format!("{} needs a share count", self.kind.label()));
+        }
+        Ok(())
+    }
+}
+
+/// Shares bought together, `cost` is per share with the buy fee spread over them.
+///
+/// `id` is the opening date, with `#2`, `#3`... added for later buys of the symbol that day.
+#[derive(Debug, serde::Deserialize, serde::Serialize, Clone)]
+pub struct Lot {
+    pub id: String,
+    pub opened: String,
+    pub shares: u32,
+    pub cost: f64,
+}
+
+/// Part of a lot closed by a sell, `price` is per share net of the sell fee.
+#[derive(Debug, serde::Deserialize, serde::Serialize, Clone)]
+pub struct ClosedLot {
+    pub opened: String,
+    pub closed: String,
+    pub shares: u32,
+    pub cost: f64,
+    pub price: f64,
+}
+
+impl ClosedLot {
+    pub fn realized_pnl(&self) -> f64 {
+        (self.price - self.cost) * self.shares as f64
+    }
+}
+
+/// Holding state after replaying every transaction of one symbol.
+#[derive(Debug, Clone, Default)]
+pub struct Position {
+    pub symbol: String,
+    pub owned: u32,
+    pub total_cost: f64,
+    pub realized_pnl: f64,
+    pub dividends: f64,
+    pub fees: f64,
+    pub lots: Vec<Lot>,
+    pub closed: Vec<ClosedLot>,
+}
+
+impl Position {
+    /// A single lot standing in for a holding that has no ledger behind it.
+    pub fn from_stock(stock: &Stock) -> Self {
+        Self {
+            symbol: stock.symbol.clone(),
+            owned: stock.owned,
+            total_cost: stock.cost_basis(),
+            lots: vec![Lot {
+                id: stock.update_at.clone(),
+                opened: stock.update_at.clone(),
+                shares: stock.owned,
+                cost: stock.cost,
+            }],
+            ..Default::default()
+        }
+    }
+
+    /// Same position with every amount multiplied by `factor`, used for currency conversion.
+    pub fn scaled(&self, factor: f64) -> Position {
+        Position {
+            total_cost: self.total_cost * factor,
+            realized_pnl: self.realized_pnl * factor,
+            dividends: self.dividends * factor,
+            fees: self.fees * factor,
+            lots: self
+                .lots
+                .iter()
+                .map(|lot| Lot {
+                    cost: lot.cost * factor,
+                    ..lot.clone()
+                })
+                .collect(),
+            closed: self
+                .closed
+                .iter()
+                .map(|lot| ClosedLot {
+                    cost: lot.cost * factor,
+                    price: lot.price * factor,
+                    ..lot.clone()
+                })
+                .collect(),
+            ..self.clone()
+        }
+    }
+
+    pub fn average_cost(&self) -> f64 {
+        if self.owned == 0 {
+            0.0
+        } else {
+            self.total_cost / self.owned as f64
+        }
+    }
+}
+
+#[derive(Debug, serde::Deserialize, serde::Serialize, Clone, Default)]
+#[serde(default)]
+pub struct Ledger {
+    transactions: Vec<Transaction>,
+}
+
+impl Ledger {
+    pub fn new(transactions: Vec<Transaction>) -> Self {
+        Self { transactions }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.transactions.is_empty()
+    }
+
+    pub fn transactions(&self) -> &[Transaction] {
+        &self.transactions
+    }
+
+    pub fn push(&mut self, transaction: Transaction) {
+        self.transactions.push(transaction);
+    }
+
+    pub fn replace(&mut self, index: usize, transaction: Transaction) {
+        if let Some(slot) = self.transactions.get_mut(index) {
+            *slot = transaction;
+        }
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        if index < self.transactions.len() {
+            self.transactions.remove(index);
+        }
+    }
+
+    /// Replays the ledger in date order, picking sold shares according to `method`.
+    pub fn replay(&self, method: CostBasis) -> Result<Vec<Position>, DataError> {
+        let mut order: Vec<usize> = (0..self.transactions.len()).collect();
+        order.sort_by(|a, b| self.transactions[*a].date.cmp(&self.transactions[*b].date));
+
+        let mut positions: Vec<Position> = vec![];
+        let mut buys: BTreeMap<(&str, &str), usize> = BTreeMap::new();
+        for i in order {
+            let transaction = &self.transactions[i];
+            transaction
+                .validate()
+                .map_err(|err| DataError::value(format!("transactions[{}]", i), err))?;
+
+            let index = match positions
+                .iter()
+                .position(|p| p.symbol == transaction.symbol)
+            {
+                Some(index) => index,
+                None => {
+                    positions.push(Position {
+                        symbol: transaction.symbol.clone(),
+                        ..Default::default()
+                    });
+                    positions.len() - 1
+                }
+            };
+            let position = &mut positions[index];
+
+            match transaction.kind {
+                TransactionKind::Buy => {
+                    let shares = transaction.shares as f64;
+                    let count = buys
+                        .entry((&transaction.symbol, &transaction.date))
+                        .or_default();
+                    *count += 1;
+                    let id = match *count {
+                        1 => transaction.date.clone(),
+                        n => format!("{}#{}", transaction.date, n),
+                    };
+                    position.owned += transaction.shares;
+                    position.total_cost += shares * transaction.price + transaction.fee;
+                    position.fees += transaction.fee;
+                    position.lots.push(Lot {
+                        id,
+                        opened: transaction.date.clone(),
+                        shares: transaction.shares,
+                        cost: transaction.price + transaction.fee / shares,
+                    });
+                }
+                TransactionKind::Sell => {
+                    if transaction.shares > position.owned {
+                        return Err(DataError::value(
+                            format!("transactions[{}].shares", i),
+                            format!(
+                                "selling {} {} but only {} held on {}",
+                                transaction.shares,
+                                transaction.symbol,
+                                position.owned,
+                                transaction.date
+                            ),
+                        ));
+                    }
+                    let average = position.average_cost();
+                    let price = transaction.price - transaction.fee / transaction.shares as f64;
+                    let mut remaining = transaction.shares;
+                    let mut sold_cost = 0.0;
+                    for lot_index in sell_order(&position.lots, method, transaction.lot.as_deref())
+                    {
+                        if remaining == 0 {
+                            break;
+                        }
+                        let lot = &mut position.lots[lot_index];
+                        let taken = remaining.min(lot.shares);
+                        let cost = match method {
+                            CostBasis::Average => average,
+                            _ => lot.cost,
+                        };
+                        lot.shares -= taken;
+                        remaining -= taken;
+                        sold_cost += cost * taken as f64;
+                        position.closed.push(ClosedLot {
+                            opened: lot.opened.clone(),
+                            closed: transaction.date.clone(),
+                            shares: taken,
+                            cost,
+                            price,
+                        });
+                    }
+                    position.lots.retain(|lot| lot.shares > 0);
+
+                    let proceeds = transaction.shares as f64 * transaction.price - transaction.fee;
+                    position.realized_pnl += proceeds - sold_cost;
+                    position.total_cost -= sold_cost;
+                    position.owned -= transaction.shares;
+                    position.fees += transaction.fee;
+                }
+                TransactionKind::Dividend => {
+                    position.realized_pnl += transaction.price - transaction.fee;
+                    position.dividends += transaction.price;
+                    position.fees += transaction.fee;
+                }
+                TransactionKind::Fee => {
+                    position.realized_pnl -= transaction.fee;
+                    position.fees += transaction.fee;
+                }
+            }
+            if position.owned == 0 {
+                position.total_cost = 0.0;
+            }
+        }
+        Ok(positions)
+    }
+
+    /// Rebuilds `holdings` from the replayed positions, keeping the market data of each symbol.
+    pub fn holdings(
+        &self,
+        market: &[Stock],
+        method: CostBasis,
+    ) -> Result<(Vec<Stock>, Vec<Position>), DataError> {
+        let positions = self.replay(method)?;
+        let holdings = positions
+            .iter()
+            .filter(|position| position.owned > 0)
+            .filter_map(|position| {
+                let Some(stock) = market.iter().find(|s| s.symbol == position.symbol) else {
+                    log::warn!("no market data for {}, skipping", position.symbol);
+                    return None;
+                };
+                Some(Stock {
+                    owned: position.owned,
+                    cost: position.average_cost(),
+                    ..stock.clone()
+                })
+            })
+            .collect();
+        Ok((holdings, positions))
+    }
+
+    /// Money moved into the holdings (positive) or taken out of them (negative), per transaction.
+    ///
+    /// Dividends leave the holdings as cash, a standalone fee is money spent without buying anything.
+    pub fn cash_flows(&self) -> Vec<Record> {
+        self.transactions
+            .iter()
+            .map(|t| {
+                let amount = t.shares as f64 * t.price;
+                let balance = match t.kind {
+                    TransactionKind::Buy => amount + t.fee,
+                    TransactionKind::Sell => -(amount - t.fee),
+                    TransactionKind::Dividend => -(t.price - t.fee),
+                    TransactionKind::Fee => t.fee,
+                };
+                Record {
+                    date: t.date.clone(),
+                    balance,
+                }
+            })
+            .collect()
+    }
+
+    /// Cost basis of everything held at the close of each day with a transaction.
+    pub fn cost_history(&self, method: CostBasis) -> Result<Vec<Record>, DataError> {
+        let mut dates: Vec<&str> = self.transactions.iter().map(|t| t.date.as_str()).collect();
+        dates.sort();
+        dates.dedup();
+        dates
+            .into_iter()
+            .map(|date| {
+                let until = Ledger::new(
+                    self.transactions
+                        .iter()
+                        .filter(|t| t.date.as_str() <= date)
+                        .cloned()
+                        .collect(),
+                );
+                let balance = until.replay(method)?.iter().map(|p| p.total_cost).sum();
+                Ok(Record {
+                    date: date.to_string(),
+                    balance,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Order in which open lots are consumed by a sell.
+///
+/// A specific-lot sell takes the lot with the id `lot` first and falls back to FIFO for the rest.
+/// The average method still depletes lots oldest first so the remaining lots stay meaningful.
+fn sell_order(lots: &[Lot], method: CostBasis, lot: Option<&str>) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..lots.len()).collect();
+    match method {
+        CostBasis::Average | CostBasis::Fifo => {}
+        CostBasis::Lifo => order.reverse(),
+        CostBasis::SpecificLot => {
+            if let Some(id) = lot {
+                order.sort_by_key(|&i| lots[i].id != id);
+            }
+        }
+    }
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modals::test_util::close;
+
+    fn trade(date: &str, kind: TransactionKind, shares: u32, price: f64, fee: f64) -> Transaction {
+        Transaction {
+            date: date.to_string(),
+            symbol: "AAA".to_string(),
+            kind,
+            shares,
+            price,
+            fee,
+            lot: None,
+        }
+    }
+
+    #[test]
+    fn replays_every_kind_in_date_order() {
+        let ledger = Ledger::new(vec![
+            trade("2024-02-01", TransactionKind::Sell, 10, 12.0, 0.0),
+            trade("2024-01-02", TransactionKind::Buy, 10, 10.0, 10.0),
+            trade("2024-01-10", TransactionKind::Dividend, 0, 5.0, 1.0),
+            trade("2024-01-15", TransactionKind::Fee, 0, 0.0, 2.0),
+        ]);
+        let positions = ledger.replay(CostBasis::Fifo).unwrap();
+        assert_eq!(positions.len(), 1);
+        let position = &positions[0];
+        assert_eq!(position.owned, 0);
+        assert_eq!(position.total_cost, 0.0);
+        assert!(position.lots.is_empty());
+        assert_eq!(position.closed.len(), 1);
+        assert!(close(position.closed[0].cost, 11.0));
+        assert!(close(position.dividends, 5.0));
+        assert!(close(position.fees, 13.0));
+        // 4 of net dividend, 2 of fee and 10 of trading gain
+        assert!(close(position.realized_pnl, 12.0));
+    }
+
+    #[test]
+    fn selling_more_than_held_is_an_error() {
+        let ledger = Ledger::new(vec![
+            trade("2024-01-02", TransactionKind::Buy, 5, 10.0, 0.0),
+            trade("2024-01-03", TransactionKind::Sell, 6, 10.0, 0.0),
+        ]);
+        let err = ledger.replay(CostBasis::Fifo).unwrap_err();
+        assert_eq!(err.path(), Some("transactions[1].shares"));
+    }
+
+    fn realized(ledger: &Ledger, method: CostBasis) -> (f64, f64) {
+        let position = &ledger.replay(method).unwrap()[0];
+        (position.realized_pnl, position.total_cost)
+    }
+
+    #[test]
+    fn cost_basis_methods_pick_different_lots() {
+        // lots cost 11 (fee included) and 20, the sell nets 435 for 15 shares
+        let ledger = Ledger::new(vec![
+            trade("2024-01-02", TransactionKind::Buy, 10, 10.0, 10.0),
+            trade("2024-02-01", TransactionKind::Buy, 10, 20.0, 0.0),
+            trade("2024-03-01", TransactionKind::Sell, 15, 30.0, 15.0),
+        ]);
+        let (pnl, cost) = realized(&ledger, CostBasis::Fifo);
+        assert!(close(pnl, 225.0) && close(cost, 100.0));
+        let (pnl, cost) = realized(&ledger, CostBasis::Lifo);
+        assert!(close(pnl, 180.0) && close(cost, 55.0));
+        let (pnl, cost) = realized(&ledger, CostBasis::Average);
+        assert!(close(pnl, 202.5) && close(cost, 77.5));
+    }
+
+    #[test]
+    fn specific_lot_tells_same_day_lots_apart() {
+        let mut sell = trade("2024-01-03", TransactionKind::Sell, 5, 30.0, 0.0);
+        sell.lot = Some("2024-01-02#2".to_string());
+        let ledger = Ledger::new(vec![
+            trade("2024-01-02", TransactionKind::Buy, 10, 10.0, 0.0),
+            trade("2024-01-02", TransactionKind::Buy, 10, 20.0, 0.0),
+            sell,
+        ]);
+        let position = &ledger.replay(CostBasis::SpecificLot).unwrap()[0];
+        assert!(close(position.realized_pnl, 50.0));
+        let ids: Vec<_> = position
+            .lots
+            .iter()
+            .map(|lot| (lot.id.as_str(), lot.shares))
+            .collect();
+        assert_eq!(ids, [("2024-01-02", 10), ("2024-01-02#2", 5)]);
+    }
+}
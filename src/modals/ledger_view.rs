@@ -0,0 +1,228 @@
+use crate::modals::currency::Currency;
+use crate::modals::format::NumberFormat;
+use crate::modals::ledger::{Ledger, Position, Transaction, TransactionKind};
+use egui::RichText;
+
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct LedgerView {
+    name: String,
+    ledger: Ledger,
+    draft: Transaction,
+    editing: Option<usize>,
+    #[serde(skip)]
+    form_error: Option<String>,
+    #[serde(skip)]
+    replay_error: Option<String>,
+    #[serde(skip)]
+    format: NumberFormat,
+    /// Open lots a sell can close, as (symbol, lot id).
+    #[serde(skip)]
+    lots: Vec<(String, String)>,
+}
+
+impl Default for LedgerView {
+    fn default() -> Self {
+        LedgerView::new("default", Ledger::default())
+    }
+}
+
+impl LedgerView {
+    pub fn new<S: AsRef<str>>(name: S, ledger: Ledger) -> Self {
+        Self {
+            name: name.as_ref().to_string(),
+            ledger,
+            draft: Transaction::default(),
+            editing: None,
+            form_error: None,
+            replay_error: None,
+            format: NumberFormat::default(),
+            lots: vec![],
+        }
+    }
+
+    pub fn ledger(&self) -> &Ledger {
+        &self.ledger
+    }
+
+    pub fn set_replay_error(&mut self, err: Option<String>) {
+        self.replay_error = err;
+    }
+
+    pub fn set_format(&mut self, format: NumberFormat) {
+        self.format = format;
+    }
+
+    pub fn set_lots(&mut self, positions: &[Position]) {
+        self.lots = positions
+            .iter()
+            .flat_map(|position| {
+                position
+                    .lots
+                    .iter()
+                    .map(|lot| (position.symbol.clone(), lot.id.clone()))
+            })
+            .collect();
+    }
+
+    /// Returns true when the ledger was changed and holdings need to be rebuilt.
+    pub fn show(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = false;
+
+        egui::Grid::new(format!("{}_form", self.name))
+            .num_columns(2)
+            .spacing([12.0, 4.0])
+            .show(ui, |ui| {
+                ui.label("Date");
+                ui.add(egui::TextEdit::singleline(&mut self.draft.date).desired_width(100.0));
+                ui.end_row();
+
+                ui.label("Symbol");
+                ui.add(egui::TextEdit::singleline(&mut self.draft.symbol).desired_width(100.0));
+                ui.end_row();
+
+                ui.label("Type");
+                egui::ComboBox::from_id_source(format!("{}_kind", self.name))
+                    .selected_text(self.draft.kind.label())
+                    .show_ui(ui, |ui| {
+                        for kind in TransactionKind::ALL {
+                            ui.selectable_value(&mut self.draft.kind, kind, kind.label());
+                        }
+                    });
+                ui.end_row();
+
+                if matches!(
+                    self.draft.kind,
+                    TransactionKind::Buy | TransactionKind::Sell
+                ) {
+                    ui.label("Shares");
+                    ui.add(egui::DragValue::new(&mut self.draft.shares));
+                    ui.end_row();
+                }
+
+                if self.draft.kind == TransactionKind::Sell {
+                    let symbol = self.draft.symbol.trim().to_uppercase();
+                    ui.label("Lot");
+                    egui::ComboBox::from_id_source(format!("{}_lot", self.name))
+                        .selected_text(self.draft.lot.as_deref().unwrap_or("Any"))
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut self.draft.lot, None, "Any");
+                            for (_, id) in self.lots.iter().filter(|(s, _)| *s == symbol) {
+                                ui.selectable_value(&mut self.draft.lot, Some(id.clone()), id);
+                            }
+                        });
+                    ui.end_row();
+                }
+
+                if self.draft.kind != TransactionKind::Fee {
+                    ui.label(match self.draft.kind {
+                        TransactionKind::Dividend => "Amount",
+                        _ => "Price",
+                    });
+                    ui.add(
+                        egui::DragValue::new(&mut self.draft.price)
+                            .speed(0.01)
+                            .clamp_range(0.0..=f64::MAX),
+                    );
+                    ui.end_row();
+                }
+
+                ui.label("Fee");
+                ui.add(
+                    egui::DragValue::new(&mut self.draft.fee)
+                        .speed(0.01)
+                        .clamp_range(0.0..=f64::MAX),
+                );
+                ui.end_row();
+            });
+
+        ui.horizontal(|ui| {
+            let label = if self.editing.is_some() {
+                "Save"
+            } else {
+                "Add"
+            };
+            if ui.button(label).clicked() {
+                self.draft.symbol = self.draft.symbol.trim().to_uppercase();
+                match self.draft.validate() {
+                    Ok(()) => {
+                        let transaction = self.draft.clone();
+                        match self.editing.take() {
+                            Some(index) => self.ledger.replace(index, transaction),
+                            None => self.ledger.push(transaction),
+                        }
+                        self.draft = Transaction {
+                            date: self.draft.date.clone(),
+                            ..Default::default()
+                        };
+                        self.form_error = None;
+                        changed = true;
+                    }
+                    Err(err) => self.form_error = Some(err),
+                }
+            }
+            if self.editing.is_some() && ui.button("Cancel").clicked() {
+                self.editing = None;
+                self.draft = Transaction::default();
+                self.form_error = None;
+            }
+        });
+
+        for err in [&self.form_error, &self.replay_error].into_iter().flatten() {
+            ui.label(
+                RichText::new(err)
+                    .size(12.0)
+                    .color(ui.visuals().error_fg_color),
+            );
+        }
+
+        ui.separator();
+
+        let mut edit = None;
+        let mut remove = None;
+        egui::ScrollArea::vertical()
+            .max_height(240.0)
+            .show(ui, |ui| {
+                egui::Grid::new(format!("{}_list", self.name))
+                    .num_columns(6)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for (index, t) in self.ledger.transactions().iter().enumerate().rev() {
+                            ui.label(RichText::new(&t.date).monospace());
+                            ui.label(RichText::new(&t.symbol).monospace());
+                            ui.label(RichText::new(t.kind.label()).monospace());
+                            // entries are recorded in the dollars they were traded in
+                            let amount = |value: f64| self.format.amount(value, Currency::Usd);
+                            let detail = match t.kind {
+                                TransactionKind::Buy | TransactionKind::Sell => {
+                                    format!("{} @ {}", t.shares, amount(t.price))
+                                }
+                                TransactionKind::Dividend => amount(t.price),
+                                TransactionKind::Fee => amount(t.fee),
+                            };
+                            ui.label(RichText::new(detail).monospace());
+                            if ui.small_button("Edit").clicked() {
+                                edit = Some(index);
+                            }
+                            if ui.small_button("Delete").clicked() {
+                                remove = Some(index);
+                            }
+                            ui.end_row();
+                        }
+                    });
+            });
+
+        if let Some(index) = edit {
+            self.draft = self.ledger.transactions()[index].clone();
+            self.editing = Some(index);
+            self.form_error = None;
+        }
+        if let Some(index) = remove {
+            self.ledger.remove(index);
+            self.editing = None;
+            changed = true;
+        }
+
+        changed
+    }
+}
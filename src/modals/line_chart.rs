@@ -1,49 +1,158 @@
+use crate::modals::calendar::{date_to_day, day_to_date, parse_records};
+use crate::modals::colors::ColorScheme;
+use crate::modals::currency::Currency;
+use crate::modals::data_reader::Record;
+use crate::modals::format::NumberFormat;
+use chrono::NaiveDate;
 use eframe::emath::Vec2;
-use egui_plot::{Legend, Line, Plot};
+use egui_plot::{Legend, Line, LineStyle, Plot};
 
 #[derive(serde::Deserialize, serde::Serialize)]
 #[serde(default)]
 pub struct LineChart {
     name: String,
-    data: Vec<f64>,
+    data: Vec<Record>,
+    cost: Vec<Record>,
+    currency: Currency,
+    format: NumberFormat,
+    show_cost: bool,
 }
 
 impl Default for LineChart {
     fn default() -> Self {
-        let data = vec![
-            0.1, 0.2, 0.5, 0.3, 0.2, 0.8, 1.2, 0.3, 1.4, 2.6, 1.3, 2.2, 2.3, 2.4,
-        ];
         Self {
             name: "default".to_string(),
-            data,
+            data: vec![],
+            cost: vec![],
+            currency: Currency::Usd,
+            format: NumberFormat::default(),
+            show_cost: true,
         }
     }
 }
 
 impl LineChart {
-    pub fn new<S: AsRef<str>>(name: S, data: Vec<f64>) -> Self {
-        return Self {
+    /// `data` is the balance over time and `cost` what had been invested by each date,
+    /// both already in `currency`.
+    pub fn new<S: AsRef<str>>(
+        name: S,
+        data: Vec<Record>,
+        cost: Vec<Record>,
+        currency: Currency,
+        format: NumberFormat,
+    ) -> Self {
+        Self {
             name: name.as_ref().to_string(),
-            data: data.clone(),
-        };
+            data,
+            cost,
+            currency,
+            format,
+            show_cost: true,
+        }
     }
 
-    #[allow(unused)]
     pub fn show(&mut self, ui: &mut egui::Ui) {
+        if !self.cost.is_empty() {
+            ui.checkbox(&mut self.show_cost, "Invested cost");
+            ui.separator();
+        }
+
+        let balance = parse_records(&self.data);
+        let last = balance.last().map(|(date, _)| *date);
+        let currency = self.currency;
+        let format = self.format;
         Plot::new(self.name.clone())
             .legend(Legend::default())
             .show_grid(false)
             .show_background(false)
             .set_margin_fraction(Vec2 { x: 0.1, y: 0.1 })
+            .y_axis_formatter(move |value, _, _| format.compact(value.value, currency))
+            .x_axis_formatter(|value, _, _| {
+                if value.value.fract() != 0.0 {
+                    return "".to_string();
+                }
+                day_to_date(value.value)
+                    .map(|date| date.format("%Y-%m-%d").to_string())
+                    .unwrap_or_default()
+            })
+            .label_formatter(move |name, value| {
+                if name.is_empty() {
+                    return "".to_string();
+                }
+                let date = day_to_date(value.x.round())
+                    .map(|date| date.format("%Y-%m-%d").to_string())
+                    .unwrap_or_default();
+                format!("{}\n{}\n{}", name, date, format.money(value.y, currency))
+            })
+            .view_aspect(1.4)
             .show(ui, |plot_ui| {
-                let points: Vec<_> = self
-                    .data
+                let points: Vec<_> = balance
                     .iter()
-                    .enumerate()
-                    .map(|(i, x)| [i as f64, *x])
+                    .map(|(date, value)| [date_to_day(*date), *value])
                     .collect();
-                let line = Line::new(points);
-                plot_ui.line(line);
+                plot_ui.line(Line::new(points).name("Balance"));
+
+                if self.show_cost && !self.cost.is_empty() {
+                    let cost = step_points(&parse_records(&self.cost), last);
+                    let line = Line::new(cost)
+                        .name("Invested cost")
+                        .style(LineStyle::dashed_loose());
+                    plot_ui.line(line);
+                }
             });
     }
 }
+
+/// Bare line of `values` sized to fit a table cell, coloured by where it ends against its start.
+pub(crate) fn sparkline(
+    ui: &mut egui::Ui,
+    id_source: impl std::hash::Hash,
+    values: &[f64],
+    size: Vec2,
+    colors: ColorScheme,
+) {
+    let color = match (values.first(), values.last()) {
+        (Some(first), Some(last)) if last < first => colors.down(),
+        _ => colors.up(),
+    };
+    let points: Vec<_> = values
+        .iter()
+        .enumerate()
+        .map(|(index, value)| [index as f64, *value])
+        .collect();
+    Plot::new(id_source)
+        .width(size.x)
+        .height(size.y)
+        .show_axes(false)
+        .show_grid(false)
+        .show_background(false)
+        .show_x(false)
+        .show_y(false)
+        .allow_drag(false)
+        .allow_zoom(false)
+        .allow_scroll(false)
+        .allow_boxed_zoom(false)
+        .allow_double_click_reset(false)
+        .sense(egui::Sense::hover())
+        .set_margin_fraction(Vec2 { x: 0.0, y: 0.1 })
+        .show(ui, |plot_ui| {
+            plot_ui.line(Line::new(points).color(color));
+        });
+}
+
+/// Holds each value flat until the next one, then carries the last one on to `end`.
+fn step_points(records: &[(NaiveDate, f64)], end: Option<NaiveDate>) -> Vec<[f64; 2]> {
+    let mut points: Vec<[f64; 2]> = vec![];
+    for (date, value) in records {
+        if let Some([_, previous]) = points.last().copied() {
+            points.push([date_to_day(*date), previous]);
+        }
+        points.push([date_to_day(*date), *value]);
+    }
+    if let (Some(end), Some((last, value))) = (end, records.last()) {
+        if end > *last {
+            points.push([date_to_day(end), *value]);
+        }
+    }
+    points
+}
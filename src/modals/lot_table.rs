@@ -0,0 +1,145 @@
+use std::collections::BTreeMap;
+
+use crate::modals::colors::ColorScheme;
+use crate::modals::currency::Currency;
+use crate::modals::format::NumberFormat;
+use crate::modals::ledger::{ClosedLot, Lot, Position};
+use egui::RichText;
+
+#[derive(serde::Deserialize, serde::Serialize, Default)]
+#[serde(default)]
+struct SymbolLots {
+    close_price: f64,
+    lots: Vec<Lot>,
+    closed: Vec<ClosedLot>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct LotTable {
+    name: String,
+    method: String,
+    symbols: BTreeMap<String, SymbolLots>,
+    currency: Currency,
+    colors: ColorScheme,
+    format: NumberFormat,
+}
+
+impl Default for LotTable {
+    fn default() -> Self {
+        Self {
+            name: "default".to_string(),
+            method: "".to_string(),
+            symbols: BTreeMap::new(),
+            currency: Currency::Usd,
+            colors: ColorScheme::RedUp,
+            format: NumberFormat::default(),
+        }
+    }
+}
+
+impl LotTable {
+    /// `data` pairs each replayed position with the latest close of its symbol.
+    pub fn new<S: AsRef<str>>(
+        name: S,
+        method: S,
+        data: &[(Position, f64)],
+        currency: Currency,
+        colors: ColorScheme,
+        format: NumberFormat,
+    ) -> Self {
+        let symbols = data
+            .iter()
+            .map(|(position, close_price)| {
+                (
+                    position.symbol.clone(),
+                    SymbolLots {
+                        close_price: *close_price,
+                        lots: position.lots.clone(),
+                        closed: position.closed.clone(),
+                    },
+                )
+            })
+            .collect();
+        Self {
+            name: name.as_ref().to_string(),
+            method: method.as_ref().to_string(),
+            symbols,
+            currency,
+            colors,
+            format,
+        }
+    }
+
+    pub fn contains(&self, symbol: &str) -> bool {
+        self.symbols.contains_key(symbol)
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui, symbol: &str) {
+        let Some(data) = self.symbols.get(symbol) else {
+            return;
+        };
+
+        ui.label(
+            RichText::new(format!("Open lots ({})", self.method))
+                .size(12.0)
+                .monospace(),
+        );
+        egui::Grid::new(format!("{}_{}_open", self.name, symbol))
+            .num_columns(5)
+            .striped(true)
+            .show(ui, |ui| {
+                for header in ["Lot", "Shares", "Cost", "Value", "P&L"] {
+                    ui.label(RichText::new(header).size(12.0).monospace());
+                }
+                ui.end_row();
+                for lot in &data.lots {
+                    let pnl = (data.close_price - lot.cost) * lot.shares as f64;
+                    ui.label(RichText::new(&lot.id).monospace());
+                    ui.label(RichText::new(lot.shares.to_string()).monospace());
+                    ui.label(
+                        RichText::new(self.format.amount(lot.cost, self.currency)).monospace(),
+                    );
+                    let value = data.close_price * lot.shares as f64;
+                    ui.label(RichText::new(self.format.amount(value, self.currency)).monospace());
+                    ui.label(self.pnl_text(pnl));
+                    ui.end_row();
+                }
+            });
+
+        if data.closed.is_empty() {
+            return;
+        }
+        ui.add_space(6.0);
+        ui.label(RichText::new("Closed lots").size(12.0).monospace());
+        egui::Grid::new(format!("{}_{}_closed", self.name, symbol))
+            .num_columns(5)
+            .striped(true)
+            .show(ui, |ui| {
+                for header in ["Opened", "Closed", "Shares", "Cost", "Realized"] {
+                    ui.label(RichText::new(header).size(12.0).monospace());
+                }
+                ui.end_row();
+                for lot in &data.closed {
+                    ui.label(RichText::new(&lot.opened).monospace());
+                    ui.label(RichText::new(&lot.closed).monospace());
+                    ui.label(RichText::new(lot.shares.to_string()).monospace());
+                    ui.label(
+                        RichText::new(self.format.amount(lot.cost, self.currency)).monospace(),
+                    );
+                    ui.label(self.pnl_text(lot.realized_pnl()));
+                    ui.end_row();
+                }
+            });
+    }
+
+    fn pnl_text(&self, pnl: f64) -> RichText {
+        let text = self.format.amount(pnl, self.currency);
+        let text = if pnl > 0.0 {
+            format!("+{}", text)
+        } else {
+            text
+        };
+        RichText::new(text).color(self.colors.of(pnl)).monospace()
+    }
+}
@@ -1,9 +1,47 @@
-pub use daily_table::DailyTable;
-pub use data_reader::PortfolioRawData;
+pub use benchmark::{Benchmark, BenchmarkChart};
+pub use candle_chart::CandleChart;
+pub use classification::{Classification, Dimension};
+pub use colors::ColorScheme;
+pub use currency::{Currency, FxRates};
+pub use daily_table::{DailyTable, TableLayout};
+pub use data_error::DataError;
+pub use data_loader::DataLoader;
+pub use data_reader::{PortfolioRawData, Record};
+pub use drawdown_chart::DrawdownChart;
+pub use format::{Locale, NumberFormat};
+pub use ledger::{CostBasis, Ledger, Position};
+pub use ledger_view::LedgerView;
+pub use line_chart::LineChart;
+pub use lot_table::LotTable;
+pub use performance::Performance;
 pub use period_chart::PeriodChart;
-pub use pie_chart::PieChart;
+pub use pie_chart::{PieChart, Slice};
+pub use rebalance::{RebalancePlan, RebalanceView};
+pub use risk::RiskPanel;
+pub use summary::Summary;
 
+mod benchmark;
+mod calendar;
+mod candle_chart;
+mod classification;
+mod colors;
+mod currency;
 mod daily_table;
+mod data_error;
+mod data_loader;
 mod data_reader;
+mod drawdown_chart;
+mod format;
+mod ledger;
+mod ledger_view;
+mod line_chart;
+mod lot_table;
+mod performance;
 mod period_chart;
 mod pie_chart;
+mod ranged_history;
+mod rebalance;
+mod risk;
+mod summary;
+#[cfg(test)]
+mod test_util;
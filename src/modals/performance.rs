@@ -0,0 +1,345 @@
+use crate::modals::calendar::value_at;
+use crate::modals::colors::ColorScheme;
+use crate::modals::currency::Currency;
+use crate::modals::data_reader::Record;
+use crate::modals::format::NumberFormat;
+use crate::modals::ranged_history::{span, RangedHistory};
+use chrono::NaiveDate;
+use egui::RichText;
+
+/// Returns of the holdings between two dates.
+#[derive(Debug, Clone, Copy)]
+pub struct Returns {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub start_value: f64,
+    pub end_value: f64,
+    pub net_flow: f64,
+    /// Time-weighted, each sub-period between two balances compounded regardless of its size.
+    pub twr: f64,
+    /// Money-weighted, the yearly rate that discounts every flow and the end value to zero.
+    pub xirr: Option<f64>,
+}
+
+impl Returns {
+    pub fn days(&self) -> i64 {
+        (self.to - self.from).num_days()
+    }
+
+    /// TWR as a yearly rate, only meaningful for periods of a year or more.
+    pub fn annualized_twr(&self) -> Option<f64> {
+        let years = self.days() as f64 / 365.0;
+        (years >= 1.0).then(|| (1.0 + self.twr).powf(1.0 / years) - 1.0)
+    }
+}
+
+/// Returns between `from` and `to` from balances and the cash flows into the holdings.
+///
+/// The period starts at the last balance on or before `from`. A flow is counted in the
+/// balance of its own day, so it is taken out of that day's change before compounding.
+pub fn returns(
+    balances: &[(NaiveDate, f64)],
+    flows: &[(NaiveDate, f64)],
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Option<Returns> {
+    let window = window(balances, from, to);
+    let start = *window.first()?;
+    let end = *window.last()?;
+    let flows: Vec<_> = flows
+        .iter()
+        .copied()
+        .filter(|(date, _)| *date > start.0 && *date <= end.0)
+        .collect();
+
+    Some(Returns {
+        from: start.0,
+        to: end.0,
+        start_value: start.1,
+        end_value: end.1,
+        net_flow: flows.iter().map(|(_, amount)| amount).sum(),
+        twr: twr(&window, &flows),
+        xirr: {
+            // seen from the investor: paying in is negative, the end value is paid back
+            let mut investor = vec![(start.0, -start.1)];
+            investor.extend(flows.iter().map(|(date, amount)| (*date, -amount)));
+            investor.push((end.0, end.1));
+            xirr(&investor)
+        },
+    })
+}
+
+/// Balances from the last one on or before `from` up to `to`.
+pub fn window(
+    balances: &[(NaiveDate, f64)],
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Vec<(NaiveDate, f64)> {
+    let Some(start) = value_at(balances, from).or_else(|| balances.first().copied()) else {
+        return vec![];
+    };
+    balances
+        .iter()
+        .copied()
+        .filter(|(date, _)| *date >= start.0 && *date <= to)
+        .collect()
+}
+
+/// Return of every step between two balances, dated at the step's end, with its flows removed.
+/// Steps starting from nothing have no return and are skipped.
+pub fn step_returns(
+    balances: &[(NaiveDate, f64)],
+    flows: &[(NaiveDate, f64)],
+) -> Vec<(NaiveDate, f64)> {
+    balances
+        .windows(2)
+        .filter(|pair| pair[0].1 > 0.0)
+        .map(|pair| {
+            let (d0, v0) = pair[0];
+            let (d1, v1) = pair[1];
+            let flow: f64 = flows
+                .iter()
+                .filter(|(date, _)| *date > d0 && *date <= d1)
+                .map(|(_, amount)| amount)
+                .sum();
+            (d1, (v1 - flow) / v0 - 1.0)
+        })
+        .collect()
+}
+
+/// Chains the step returns into one.
+pub fn twr(balances: &[(NaiveDate, f64)], flows: &[(NaiveDate, f64)]) -> f64 {
+    step_returns(balances, flows)
+        .iter()
+        .map(|(_, r)| 1.0 + r)
+        .product::<f64>()
+        - 1.0
+}
+
+/// Yearly rate at which the dated flows have a net present value of zero.
+///
+/// Needs at least one positive and one negative flow. Newton's method is tried first,
+/// bisection takes over when it wanders off.
+pub fn xirr(flows: &[(NaiveDate, f64)]) -> Option<f64> {
+    if !flows.iter().any(|(_, a)| *a > 0.0) || !flows.iter().any(|(_, a)| *a < 0.0) {
+        return None;
+    }
+    let t0 = flows.iter().map(|(date, _)| *date).min()?;
+    let years: Vec<(f64, f64)> = flows
+        .iter()
+        .map(|(date, amount)| ((*date - t0).num_days() as f64 / 365.0, *amount))
+        .collect();
+    let npv = |rate: f64| -> f64 {
+        years
+            .iter()
+            .map(|(t, amount)| amount / (1.0 + rate).powf(*t))
+            .sum()
+    };
+    let slope = |rate: f64| -> f64 {
+        years
+            .iter()
+            .map(|(t, amount)| -t * amount / (1.0 + rate).powf(t + 1.0))
+            .sum()
+    };
+
+    let mut rate = 0.1;
+    for _ in 0..50 {
+        let (value, d) = (npv(rate), slope(rate));
+        if value.abs() < 1e-9 {
+            return Some(rate);
+        }
+        if d == 0.0 || !d.is_finite() {
+            break;
+        }
+        let next = rate - value / d;
+        if !next.is_finite() || next <= -1.0 {
+            break;
+        }
+        rate = next;
+    }
+
+    let (mut low, mut high) = (-0.9999, 1.0);
+    while npv(low).signum() == npv(high).signum() {
+        high *= 2.0;
+        if high > 1e6 {
+            return None;
+        }
+    }
+    for _ in 0..200 {
+        let mid = (low + high) / 2.0;
+        if npv(mid).signum() == npv(low).signum() {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    Some((low + high) / 2.0)
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Performance {
+    name: String,
+    history: RangedHistory,
+    currency: Currency,
+    colors: ColorScheme,
+    format: NumberFormat,
+    #[serde(skip)]
+    returns: Option<Returns>,
+}
+
+impl Default for Performance {
+    fn default() -> Self {
+        Performance::new(
+            "default",
+            vec![],
+            vec![],
+            Currency::Usd,
+            ColorScheme::RedUp,
+            NumberFormat::default(),
+        )
+    }
+}
+
+impl Performance {
+    /// `data` is the balance history and `flows` the money moved into the holdings,
+    /// both in `currency`.
+    pub fn new<S: AsRef<str>>(
+        name: S,
+        data: Vec<Record>,
+        flows: Vec<Record>,
+        currency: Currency,
+        colors: ColorScheme,
+        format: NumberFormat,
+    ) -> Self {
+        let mut performance = Self {
+            name: name.as_ref().to_string(),
+            history: RangedHistory::new(data, flows),
+            currency,
+            colors,
+            format,
+            returns: None,
+        };
+        performance.compute();
+        performance
+    }
+
+    pub fn set_range(&mut self, range: Option<(NaiveDate, NaiveDate)>) {
+        if self.history.set_range(range) {
+            self.compute();
+        }
+    }
+
+    fn compute(&mut self) {
+        let (balances, flows) = self.history.parsed();
+        self.returns = span(&balances).and_then(|(from, to)| returns(&balances, &flows, from, to));
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui) {
+        let Some(returns) = self.returns else {
+            ui.label(RichText::new("Not enough history").size(12.0).monospace());
+            return;
+        };
+        let percent = |value: Option<f64>| -> RichText {
+            match value {
+                Some(value) => {
+                    RichText::new(format!("{:+.2}%", value * 100.0)).color(self.colors.of(value))
+                }
+                None => RichText::new("-"),
+            }
+            .size(12.0)
+            .monospace()
+        };
+        egui::Grid::new(format!("{}_grid", self.name))
+            .num_columns(2)
+            .spacing([24.0, 2.0])
+            .show(ui, |ui| {
+                ui.label(RichText::new("Period").size(12.0).monospace());
+                ui.label(
+                    RichText::new(format!("{} ~ {}", returns.from, returns.to))
+                        .size(12.0)
+                        .monospace(),
+                );
+                ui.end_row();
+
+                ui.label(RichText::new("Start value").size(12.0).monospace());
+                ui.label(
+                    RichText::new(self.format.money(returns.start_value, self.currency))
+                        .size(12.0)
+                        .monospace(),
+                );
+                ui.end_row();
+
+                ui.label(RichText::new("End value").size(12.0).monospace());
+                ui.label(
+                    RichText::new(self.format.money(returns.end_value, self.currency))
+                        .size(12.0)
+                        .monospace(),
+                );
+                ui.end_row();
+
+                ui.label(RichText::new("Net flows").size(12.0).monospace());
+                ui.label(
+                    RichText::new(self.format.money(returns.net_flow, self.currency))
+                        .size(12.0)
+                        .monospace(),
+                );
+                ui.end_row();
+
+                ui.label(RichText::new("TWR").size(12.0).monospace())
+                    .on_hover_text("Time-weighted return, deposits and withdrawals left out");
+                ui.label(percent(Some(returns.twr)));
+                ui.end_row();
+
+                ui.label(RichText::new("TWR / year").size(12.0).monospace());
+                ui.label(percent(returns.annualized_twr()));
+                ui.end_row();
+
+                ui.label(RichText::new("XIRR").size(12.0).monospace())
+                    .on_hover_text("Money-weighted yearly return, timing of flows included");
+                ui.label(percent(returns.xirr));
+                ui.end_row();
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modals::test_util::{close, date, series};
+
+    #[test]
+    fn flow_on_a_step_boundary_belongs_to_that_step() {
+        // 50 paid in on the 2nd with no gain, then 10% on the 3rd
+        let balances = series(&[
+            ("2024-01-01", 100.0),
+            ("2024-01-02", 150.0),
+            ("2024-01-03", 165.0),
+        ]);
+        let flows = series(&[("2024-01-02", 50.0)]);
+        let steps = step_returns(&balances, &flows);
+        assert!(close(steps[0].1, 0.0) && close(steps[1].1, 0.1));
+        assert!(close(twr(&balances, &flows), 0.1));
+
+        let all = returns(&balances, &flows, date("2024-01-01"), date("2024-01-03")).unwrap();
+        assert!(close(all.net_flow, 50.0) && close(all.twr, 0.1));
+        // starting on the 2nd, the flow is already part of the start value
+        let late = returns(&balances, &flows, date("2024-01-02"), date("2024-01-03")).unwrap();
+        assert_eq!(late.start_value, 150.0);
+        assert!(close(late.net_flow, 0.0) && close(late.twr, 0.1));
+    }
+
+    #[test]
+    fn xirr_matches_closed_form() {
+        // one payment grown over two years of 365 days: (121 / 100)^(1 / 2) - 1
+        let flows = series(&[("2023-01-01", -100.0), ("2025-01-01", 121.0)]);
+        let days = (date("2025-01-01") - date("2023-01-01")).num_days() as f64;
+        let expected = (121.0_f64 / 100.0).powf(365.0 / days) - 1.0;
+        assert!(close(xirr(&flows).unwrap(), expected));
+        assert_eq!(xirr(&series(&[("2023-01-01", -100.0)])), None);
+
+        // halving the money in a year is a rate of -50%
+        let flows = series(&[("2023-01-01", -100.0), ("2024-01-01", 50.0)]);
+        assert!(close(xirr(&flows).unwrap(), -0.5));
+    }
+}
@@ -1,35 +1,40 @@
+use crate::modals::calendar::{bucketize, parse_records, Period, RangePreset};
+use crate::modals::colors::ColorScheme;
+use crate::modals::currency::{Currency, FxRates};
 use crate::modals::data_reader::Record;
-use chrono::{Datelike, Local, NaiveDate};
+use crate::modals::format::NumberFormat;
+use chrono::{Local, NaiveDate};
 use eframe::emath::Vec2;
-use egui_plot::{Bar, BarChart, Plot};
+use egui_plot::{Bar, BarChart, Legend, Plot, PlotBounds};
 
-pub enum Period {
-    Daily,
-    Monthly,
-    Yearly,
-}
-
-fn format_balance(value: f64) -> String {
-    if value.abs() >= 1_000_000_000_000.0 {
-        format!("{:.2}T$", value / 1_000_000_000_000.0)
-    } else if value.abs() >= 1_000_000_000.0 {
-        format!("{:.2}B$", value / 1_000_000_000.0)
-    } else if value.abs() >= 1_000_000.0 {
-        format!("{:.2}M$", value / 1_000_000.0)
-    } else if value.abs() >= 1_000.0 {
-        format!("{:.2}k$", value / 1_000.0)
-    } else {
-        format!("{:.2}$", value)
-    }
-}
+/// Bars visible when no range has been picked yet.
+const DEFAULT_BARS: usize = 6;
 
 #[derive(serde::Deserialize, serde::Serialize)]
 #[serde(default)]
 pub struct PeriodChart {
     name: String,
     data: Vec<Record>,
+    symbols: Vec<(String, Vec<Record>)>,
+    symbol: Option<String>,
+    period: Period,
+    preset: Option<RangePreset>,
+    from: String,
+    to: String,
+    currency: Currency,
+    #[serde(skip)]
+    fx: FxRates,
+    colors: ColorScheme,
+    format: NumberFormat,
+    fx_split: bool,
     view_data: Vec<f64>,
+    usd_data: Vec<f64>,
+    rates: Vec<f64>,
     x_axis: Vec<String>,
+    #[serde(skip)]
+    spans: Vec<(NaiveDate, NaiveDate)>,
+    #[serde(skip)]
+    reset_view: bool,
 }
 
 impl Default for PeriodChart {
@@ -38,83 +43,281 @@ impl Default for PeriodChart {
         Self {
             name: "default".to_string(),
             data,
+            symbols: vec![],
+            symbol: None,
+            period: Period::Daily,
+            preset: None,
+            from: String::new(),
+            to: String::new(),
+            currency: Currency::Usd,
+            fx: FxRates::default(),
+            colors: ColorScheme::RedUp,
+            format: NumberFormat::default(),
+            fx_split: false,
             view_data: vec![],
+            usd_data: vec![],
+            rates: vec![],
             x_axis: vec![],
+            spans: vec![],
+            reset_view: true,
         }
     }
 }
 
 impl PeriodChart {
-    pub fn new<S: AsRef<str>>(name: S, data: Vec<Record>) -> Self {
+    /// `symbols` holds the value series of each holding, shown instead of `data` when selected.
+    /// Balances are in USD and converted to `currency` with the rate of their own date.
+    pub fn new<S: AsRef<str>>(
+        name: S,
+        data: Vec<Record>,
+        symbols: Vec<(String, Vec<Record>)>,
+        currency: Currency,
+        fx: FxRates,
+        colors: ColorScheme,
+        format: NumberFormat,
+    ) -> Self {
         let mut chart = PeriodChart {
             name: name.as_ref().to_string(),
             data: data.clone(),
+            symbols,
+            symbol: None,
+            period: Period::Daily,
+            preset: None,
+            from: String::new(),
+            to: String::new(),
+            currency,
+            fx,
+            colors,
+            format,
+            fx_split: false,
             view_data: vec![],
+            usd_data: vec![],
+            rates: vec![],
             x_axis: vec![],
+            spans: vec![],
+            reset_view: true,
         };
         chart.set_type(Period::Daily);
         chart
     }
 
+    fn records(&self) -> &[Record] {
+        self.symbol
+            .as_ref()
+            .and_then(|symbol| self.symbols.iter().find(|(s, _)| s == symbol))
+            .map(|(_, records)| records.as_slice())
+            .unwrap_or(&self.data)
+    }
+
     pub fn find_most_recent_date(&self) -> NaiveDate {
-        self.data
+        self.records()
             .iter()
             .filter_map(|record| NaiveDate::parse_from_str(&record.date, "%Y-%m-%d").ok())
             .max()
             .unwrap_or(Local::now().date_naive())
     }
 
+    /// Buckets the whole history, the date range only decides which bars are in view.
     pub fn set_type(&mut self, period: Period) {
-        let today = self.find_most_recent_date();
-        let mut target_date = vec![];
-        target_date.push(today.to_string());
-        for i in 0..6 {
-            let day = match period {
-                Period::Daily => {
-                    NaiveDate::from_ymd_opt(today.year(), today.month(), today.day() - i)
-                }
-                Period::Monthly => NaiveDate::from_ymd_opt(today.year(), today.month() - i, 1),
-                Period::Yearly => NaiveDate::from_ymd_opt(today.year() - i as i32, 1, 1),
-            };
-            target_date.push(day.unwrap().to_string());
-        }
-        target_date.sort();
-        let filtered: Vec<_> = self
-            .data
+        self.period = period;
+        let latest = self.find_most_recent_date();
+        let records = parse_records(self.records());
+        let first = records.first().map(|(date, _)| *date).unwrap_or(latest);
+        // the first bucket is the baseline of the first bar
+        let count = period.count_between(first, latest);
+        let buckets: Vec<_> = bucketize(&records, period, count, latest)
+            .into_iter()
+            .filter(|bucket| bucket.value.is_some())
+            .collect();
+
+        let rates: Vec<f64> = buckets
             .iter()
-            .filter(|r| target_date.contains(&r.date))
+            .map(|b| {
+                let date = b.date.map(|date| date.to_string());
+                self.fx.factor(self.currency, date.as_deref())
+            })
             .collect();
+        let usd_data: Vec<f64> = buckets.iter().filter_map(|b| b.value).collect();
+        let x_axis = buckets.iter().skip(1).map(|b| b.label.clone()).collect();
+        self.spans = buckets.iter().skip(1).map(|b| (b.start, b.end)).collect();
+        self.view_data = usd_data.iter().zip(&rates).map(|(b, r)| b * r).collect();
+        self.usd_data = usd_data;
+        self.rates = rates;
+        self.x_axis = x_axis;
+        self.apply_range(first, latest);
+    }
 
-        self.view_data = filtered.iter().map(|r| r.balance).collect();
-        self.x_axis = filtered.iter().skip(1).map(|r| r.date.clone()).collect();
+    /// Resolves the preset or the typed dates into `from`/`to`,
+    /// falling back to the last few bars when neither gives a usable range.
+    fn apply_range(&mut self, first: NaiveDate, latest: NaiveDate) {
+        let range = match self.preset {
+            Some(preset) => Some((preset.from(first, latest), latest)),
+            None => parse_date(&self.from)
+                .zip(parse_date(&self.to))
+                .filter(|(from, to)| from <= to),
+        };
+        let range = range.or_else(|| {
+            let start = self.spans.len().saturating_sub(DEFAULT_BARS);
+            self.spans
+                .get(start)
+                .zip(self.spans.last())
+                .map(|(first, last)| (first.0, last.1))
+        });
+        if let Some((from, to)) = range {
+            self.from = from.to_string();
+            self.to = to.to_string();
+        }
+        self.reset_view = true;
+    }
+
+    /// Shows the series of `symbol`, or the whole portfolio for `None` or an unknown symbol.
+    pub fn set_symbol(&mut self, symbol: Option<&str>) {
+        let symbol = symbol.filter(|symbol| self.symbols.iter().any(|(s, _)| s == symbol));
+        if self.symbol.as_deref() != symbol {
+            self.symbol = symbol.map(|symbol| symbol.to_string());
+            self.set_type(self.period);
+        }
+    }
+
+    /// The selected date range, if it parses.
+    pub fn range(&self) -> Option<(NaiveDate, NaiveDate)> {
+        parse_date(&self.from).zip(parse_date(&self.to))
+    }
+
+    /// Indices of the first and last bar overlapping the selected range.
+    fn visible(&self) -> Option<(usize, usize)> {
+        let from = parse_date(&self.from)?;
+        let to = parse_date(&self.to)?;
+        let first = self.spans.iter().position(|(_, end)| *end >= from)?;
+        let last = self.spans.iter().rposition(|(start, _)| *start <= to)?;
+        (first <= last).then_some((first, last))
+    }
+
+    /// Takes the range back from the plot after the user dragged or zoomed it.
+    fn sync_range(&mut self, bounds: &PlotBounds) {
+        let Some(last_bar) = self.spans.len().checked_sub(1) else {
+            return;
+        };
+        let first = (bounds.min()[0].ceil().max(0.0) as usize).min(last_bar);
+        let last = (bounds.max()[0].floor().max(0.0) as usize).min(last_bar);
+        if first <= last {
+            self.preset = None;
+            self.from = self.spans[first].0.to_string();
+            self.to = self.spans[last].1.to_string();
+        }
+    }
+
+    /// Value extent of the bars between `min_x` and `max_x`, with some headroom.
+    fn y_range(&self, min_x: f64, max_x: f64, fx_split: bool) -> (f64, f64) {
+        let (mut low, mut high) = (0.0_f64, 0.0_f64);
+        let parts = if fx_split {
+            attribution(&self.usd_data, &self.rates)
+        } else {
+            vec![]
+        };
+        let first = min_x.ceil().max(0.0) as usize;
+        let last = max_x.floor().max(0.0) as usize;
+        for i in first..=last {
+            let (Some(v0), Some(v1)) = (self.view_data.get(i), self.view_data.get(i + 1)) else {
+                break;
+            };
+            let mut values = vec![v1 - v0];
+            if let Some(&(asset, fx)) = parts.get(i) {
+                // gains stack upward and losses downward, so each side reaches its own sum
+                values.push(asset.max(0.0) + fx.max(0.0));
+                values.push(asset.min(0.0) + fx.min(0.0));
+            }
+            for value in values {
+                low = low.min(value);
+                high = high.max(value);
+            }
+        }
+        if high - low <= f64::EPSILON {
+            high = low + 1.0;
+        }
+        let margin = (high - low) * 0.07;
+        (low - margin, high + margin)
     }
 
     pub fn show(&mut self, ui: &mut egui::Ui) {
-        ui.horizontal(|button_ui| {
-            button_ui.add_space(6.0);
-            if button_ui.button("Daily").clicked() {
-                self.set_type(Period::Daily)
+        ui.horizontal_wrapped(|button_ui| {
+            for period in Period::ALL {
+                button_ui.add_space(6.0);
+                if button_ui
+                    .selectable_label(self.period == period, period.label())
+                    .clicked()
+                {
+                    self.set_type(period)
+                }
             }
             button_ui.add_space(6.0);
-            if button_ui.button("Monthly").clicked() {
-                self.set_type(Period::Monthly)
+            let before = self.symbol.clone();
+            egui::ComboBox::from_id_source(format!("{}_symbol", self.name))
+                .selected_text(self.symbol.as_deref().unwrap_or("Portfolio"))
+                .show_ui(button_ui, |ui| {
+                    ui.selectable_value(&mut self.symbol, None, "Portfolio");
+                    for (symbol, _) in &self.symbols {
+                        ui.selectable_value(&mut self.symbol, Some(symbol.clone()), symbol);
+                    }
+                });
+            if self.symbol != before {
+                self.set_type(self.period);
             }
-            button_ui.add_space(6.0);
-            if button_ui.button("Yearly").clicked() {
-                self.set_type(Period::Yearly)
+            if self.currency != Currency::Usd {
+                button_ui.add_space(6.0);
+                button_ui
+                    .checkbox(&mut self.fx_split, "FX split")
+                    .on_hover_text("Split each bar into asset return and currency return");
+            }
+        });
+
+        ui.horizontal_wrapped(|range_ui| {
+            for preset in RangePreset::ALL {
+                range_ui.add_space(6.0);
+                if range_ui
+                    .selectable_label(self.preset == Some(preset), preset.label())
+                    .clicked()
+                {
+                    self.preset = Some(preset);
+                    self.set_type(self.period);
+                }
+            }
+            range_ui.add_space(6.0);
+            let from = range_ui.add(egui::TextEdit::singleline(&mut self.from).desired_width(80.0));
+            range_ui.label("~");
+            let to = range_ui.add(egui::TextEdit::singleline(&mut self.to).desired_width(80.0));
+            if from.lost_focus() || to.lost_focus() {
+                self.preset = None;
+                self.set_type(self.period);
             }
         });
 
         ui.separator();
 
         let x_axis = self.x_axis.clone();
-        Plot::new(self.name.clone())
+        let currency = self.currency;
+        let format = self.format;
+        let fx_split = self.fx_split && self.currency != Currency::Usd;
+        let mut plot = Plot::new(self.name.clone());
+        if fx_split {
+            plot = plot.legend(Legend::default());
+        }
+        let visible = self.visible();
+        let reset_view = std::mem::take(&mut self.reset_view);
+        let response = plot
             .show_background(false)
             .show_grid(false)
-            .allow_scroll(false)
+            .allow_drag([true, false])
+            .allow_zoom([true, false])
+            .allow_scroll([true, false])
+            .allow_boxed_zoom(false)
+            .allow_double_click_reset(false)
             .set_margin_fraction(Vec2 { x: 0.15, y: 0.07 })
-            .y_axis_formatter(|value, _, _| format_balance(value.value))
+            .y_axis_formatter(move |value, _, _| format.compact(value.value, currency))
             .x_axis_formatter(move |value, _, _| {
+                if value.value < 0.0 || value.value.fract() != 0.0 {
+                    return "".to_string();
+                }
                 x_axis
                     .get(value.value as usize)
                     .unwrap_or(&"".to_string())
@@ -122,14 +325,109 @@ impl PeriodChart {
             })
             .view_aspect(1.4)
             .show(ui, |plot_ui| {
-                let bars: Vec<_> = self
-                    .view_data
-                    .windows(2)
-                    .enumerate()
-                    .map(|(i, window)| Bar::new(i as f64, window[1] - window[0]))
-                    .collect();
-                let chart = BarChart::new(bars);
-                plot_ui.bar_chart(chart);
+                if let Some((first, last)) = visible {
+                    // x follows the picked range or the user's drag, y always fits what is in view
+                    let (min_x, max_x) = if reset_view {
+                        (first as f64 - 0.5, last as f64 + 0.5)
+                    } else {
+                        let bounds = plot_ui.plot_bounds();
+                        (bounds.min()[0], bounds.max()[0])
+                    };
+                    let (min_y, max_y) = self.y_range(min_x, max_x, fx_split);
+                    plot_ui
+                        .set_plot_bounds(PlotBounds::from_min_max([min_x, min_y], [max_x, max_y]));
+                }
+                if fx_split {
+                    let (asset, fx) = attribution(&self.usd_data, &self.rates)
+                        .into_iter()
+                        .enumerate()
+                        .map(|(i, (asset, fx))| {
+                            (
+                                Bar::new(i as f64, asset),
+                                Bar::new(i as f64, fx).base_offset(fx_base(asset, fx)),
+                            )
+                        })
+                        .unzip();
+                    plot_ui.bar_chart(BarChart::new(asset).name("Asset"));
+                    plot_ui.bar_chart(BarChart::new(fx).name("FX"));
+                } else {
+                    let bars: Vec<_> = self
+                        .view_data
+                        .windows(2)
+                        .enumerate()
+                        .map(|(i, window)| {
+                            let change = window[1] - window[0];
+                            Bar::new(i as f64, change).fill(self.colors.of(change))
+                        })
+                        .collect();
+                    let chart = BarChart::new(bars);
+                    plot_ui.bar_chart(chart);
+                }
             });
+
+        let moved = response.response.dragged()
+            || (response.response.hovered()
+                && ui.input(|i| i.smooth_scroll_delta.x != 0.0 || i.zoom_delta() != 1.0));
+        if moved {
+            self.sync_range(response.transform.bounds());
+        }
+    }
+}
+
+/// Splits each change of the converted balance into what the holdings earned in USD,
+/// valued at the opening rate, and what the rate move added on the closing USD balance,
+/// as (asset, fx). The two parts always add up to the plain bar.
+fn attribution(usd: &[f64], rates: &[f64]) -> Vec<(f64, f64)> {
+    usd.windows(2)
+        .zip(rates.windows(2))
+        .map(|(usd, rate)| {
+            let asset = (usd[1] - usd[0]) * rate[0];
+            let fx = usd[1] * (rate[1] - rate[0]);
+            (asset, fx)
+        })
+        .collect()
+}
+
+/// Where the FX bar starts, on top of the asset bar only when both go the same way.
+fn fx_base(asset: f64, fx: f64) -> f64 {
+    if asset * fx > 0.0 {
+        asset
+    } else {
+        0.0
+    }
+}
+
+fn parse_date(date: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d").ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn attribution_adds_up_to_the_converted_change() {
+        let usd = [100.0, 110.0, 105.0, 120.0];
+        let rates = [1300.0, 1350.0, 1320.0, 1320.0];
+        let parts = attribution(&usd, &rates);
+        assert_eq!(parts.len(), 3);
+        for (i, (asset, fx)) in parts.iter().enumerate() {
+            let plain = usd[i + 1] * rates[i + 1] - usd[i] * rates[i];
+            assert!((asset + fx - plain).abs() < 1e-6);
+        }
+        // a flat rate leaves nothing to the currency
+        assert_eq!(parts[2], (15.0 * 1320.0, 0.0));
+    }
+
+    #[test]
+    fn opposite_parts_are_not_stacked() {
+        // the holdings gain while the won strengthens
+        let parts = attribution(&[100.0, 110.0], &[1350.0, 1300.0]);
+        let (asset, fx) = parts[0];
+        assert!(asset > 0.0 && fx < 0.0);
+        assert_eq!(fx_base(asset, fx), 0.0);
+        assert_eq!(fx_base(-asset, fx), -asset);
+        assert_eq!(fx_base(asset, -fx), asset);
+        assert_eq!(fx_base(0.0, fx), 0.0);
     }
 }
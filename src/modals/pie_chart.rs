@@ -1,69 +1,211 @@
 use std::f64::consts::TAU;
 
+use crate::modals::colors::ColorScheme;
+use crate::modals::currency::Currency;
+use crate::modals::format::NumberFormat;
 use egui::{Align2, Color32, RichText, Stroke};
 use egui_plot::{Plot, PlotPoint, PlotPoints, Polygon, Text};
 
 const FULL_CIRCLE_VERTICES: f64 = 120.0;
 const RADIUS: f64 = 1.0;
 const R_TAU: f64 = 1.0 / TAU;
+const OTHER: &str = "Other";
+
+/// Colors of the first holdings, later ones get generated hues.
+const COLOR_PRESET: [Color32; 6] = [
+    Color32::LIGHT_BLUE,
+    Color32::KHAKI,
+    Color32::LIGHT_RED,
+    Color32::LIGHT_YELLOW,
+    Color32::LIGHT_GREEN,
+    Color32::LIGHT_GRAY,
+];
+
+/// Color of the sector at `index`, the same for a given index on every run.
+fn palette(index: usize, colors: ColorScheme) -> Color32 {
+    match colors.category(index).or(COLOR_PRESET.get(index).copied()) {
+        Some(color) => color,
+        None => {
+            // golden-ratio steps around the hue circle keep neighbours apart
+            let hue = (index as f32 * 0.618_034).fract();
+            egui::ecolor::Hsva::new(hue, 0.45, 0.95, 1.0).into()
+        }
+    }
+}
+
+/// One holding as shown in the pie, the extra figures go into the hover tooltip.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Default)]
+#[serde(default)]
+pub struct Slice {
+    pub label: String,
+    pub value: f64,
+    pub shares: u32,
+    pub pnl: f64,
+}
 
 #[derive(serde::Deserialize, serde::Serialize)]
 #[serde(default)]
 pub struct PieChart {
     name: String,
+    data: Vec<Slice>,
     sectors: Vec<Sector>,
-    color_preset: Vec<Color32>,
+    currency: Currency,
+    colors: ColorScheme,
+    format: NumberFormat,
+    selected: Option<String>,
+    /// Merge holdings below `threshold` percent into one "Other" sector.
+    group_other: bool,
+    threshold: f64,
+    /// Show the members of "Other" as their own sectors again.
+    expanded: bool,
 }
 
 impl Default for PieChart {
     fn default() -> Self {
-        PieChart::new("Default", &[(14.0, "100%")])
+        let slice = Slice {
+            label: "100%".to_string(),
+            value: 14.0,
+            ..Default::default()
+        };
+        PieChart::new(
+            "Default",
+            &[slice],
+            Currency::Usd,
+            ColorScheme::RedUp,
+            NumberFormat::default(),
+        )
     }
 }
 impl PieChart {
-    pub fn new<S: AsRef<str>, L: AsRef<str>>(name: S, data: &[(f64, L)]) -> Self {
-        let sum: f64 = data.iter().map(|(f, _)| f).sum();
+    pub fn new<S: AsRef<str>>(
+        name: S,
+        data: &[Slice],
+        currency: Currency,
+        colors: ColorScheme,
+        format: NumberFormat,
+    ) -> Self {
+        let mut chart = Self {
+            name: name.as_ref().to_string(),
+            data: data.to_vec(),
+            sectors: vec![],
+            currency,
+            colors,
+            format,
+            selected: None,
+            group_other: false,
+            threshold: 5.0,
+            expanded: false,
+        };
+        chart.layout();
+        chart
+    }
 
-        let slices: Vec<_> = data.iter().map(|(f, n)| (f / sum, n)).collect();
+    /// Grouping options of another chart, so they survive a rebuild.
+    pub fn keep_grouping(&mut self, other: &PieChart) {
+        self.group_other = other.group_other;
+        self.threshold = other.threshold;
+        self.expanded = other.expanded;
+        self.layout();
+    }
+
+    fn layout(&mut self) {
+        let sum: f64 = self.data.iter().map(|slice| slice.value).sum();
+
+        // (slice, color, members) with the small holdings folded into one slice
+        let mut slices: Vec<(Slice, Color32, Vec<String>)> = vec![];
+        let mut other = Slice {
+            label: OTHER.to_string(),
+            ..Default::default()
+        };
+        let mut members = vec![];
+        for (index, slice) in self.data.iter().enumerate() {
+            let percent = slice.value / sum * 100.0;
+            if self.group_other && !self.expanded && percent < self.threshold {
+                other.value += slice.value;
+                other.shares += slice.shares;
+                other.pnl += slice.pnl;
+                members.push(slice.label.clone());
+            } else {
+                slices.push((slice.clone(), palette(index, self.colors), vec![]));
+            }
+        }
+        match members.len() {
+            0 => {}
+            // a single small holding is clearer on its own
+            1 => {
+                let index = self
+                    .data
+                    .iter()
+                    .position(|slice| slice.label == members[0])
+                    .unwrap_or(0);
+                slices.push((
+                    self.data[index].clone(),
+                    palette(index, self.colors),
+                    vec![],
+                ));
+            }
+            _ => slices.push((other, Color32::GRAY, members)),
+        }
 
         let step = TAU / FULL_CIRCLE_VERTICES;
 
         let mut offset = 0.0_f64;
 
-        let sectors = slices
-            .iter()
-            .map(|(p, n)| {
+        self.sectors = slices
+            .into_iter()
+            .map(|(slice, color, members)| {
+                let p = slice.value / sum;
                 let vertices = (FULL_CIRCLE_VERTICES * p).round() as usize;
 
                 let start = TAU * offset;
                 let end = TAU * (offset + p);
 
-                let sector = Sector::new(n, start, end, vertices, step);
+                let mut sector = Sector::new(&slice.label, start, end, vertices, step);
+                sector.slice = slice;
+                sector.color = color;
+                sector.members = members;
 
                 offset += p;
 
                 sector
             })
             .collect();
+    }
 
-        let color_preset = vec![
-            Color32::LIGHT_BLUE,
-            Color32::KHAKI,
-            Color32::LIGHT_RED,
-            Color32::LIGHT_YELLOW,
-            Color32::LIGHT_GREEN,
-            Color32::LIGHT_GRAY,
-        ];
-        Self {
-            name: name.as_ref().to_string(),
-            sectors,
-            color_preset,
-        }
+    pub fn selected(&self) -> Option<&str> {
+        self.selected.as_deref()
     }
 
-    pub fn show(&mut self, ui: &mut egui::Ui) {
+    pub fn select(&mut self, label: Option<&str>) {
+        self.selected = label.map(|label| label.to_string());
+    }
+
+    /// Returns true when a click changed the selected sector.
+    pub fn show(&mut self, ui: &mut egui::Ui) -> bool {
+        ui.horizontal(|ui| {
+            let mut changed = ui.checkbox(&mut self.group_other, "Other below").changed();
+            changed |= ui
+                .add_enabled(
+                    self.group_other,
+                    egui::DragValue::new(&mut self.threshold)
+                        .clamp_range(0.0..=50.0)
+                        .speed(0.1)
+                        .suffix("%"),
+                )
+                .changed();
+            if self.group_other && self.expanded && ui.small_button("Collapse").clicked() {
+                self.expanded = false;
+                changed = true;
+            }
+            if changed {
+                self.layout();
+            }
+        });
+
         let sectors = self.sectors.clone();
-        Plot::new(&self.name)
+        let mut hovered = None;
+        let mut clicked = false;
+        let response = Plot::new(&self.name)
             .show_background(false)
             .show_axes([false; 2])
             .allow_boxed_zoom(false)
@@ -76,30 +218,110 @@ impl PieChart {
             .data_aspect(1.0)
             .show_grid([false; 2])
             .show(ui, |plot_ui| {
+                hovered = plot_ui
+                    .pointer_coordinate()
+                    .and_then(|point| sectors.iter().position(|s| s.contains(&point)));
+                clicked = plot_ui.response().clicked();
                 for (index, sector) in sectors.into_iter().enumerate() {
                     let Sector {
                         name,
                         points,
                         center,
                         percent,
+                        color,
+                        members,
                         ..
                     } = sector;
 
-                    let stroke = Stroke::new(1.0, self.color_preset[index]);
-                    plot_ui.polygon(
-                        Polygon::new(PlotPoints::new(points))
-                            .name(&name)
-                            .stroke(stroke),
-                    );
+                    let selected = self.selected.as_deref();
+                    let highlighted = hovered == Some(index)
+                        || selected == Some(name.as_str())
+                        || selected.is_some_and(|s| members.iter().any(|m| m == s));
+                    let polygon = Polygon::new(PlotPoints::new(points)).name(&name);
+                    let polygon = if highlighted {
+                        polygon
+                            .stroke(Stroke::new(2.5, color))
+                            .fill_color(color.gamma_multiply(0.35))
+                    } else {
+                        polygon.stroke(Stroke::new(1.0, color))
+                    };
+                    plot_ui.polygon(polygon);
                     let text = RichText::new(format!("{}\n{:.2}%", &name, percent))
                         .monospace()
-                        .color(self.color_preset[index]);
+                        .color(color);
                     plot_ui.text(
                         Text::new(PlotPoint::new(center[0], center[1]), text)
                             .anchor(Align2::CENTER_CENTER),
                     );
                 }
             });
+
+        if let Some(sector) = hovered.and_then(|index| self.sectors.get(index)) {
+            let currency = self.currency;
+            let colors = self.colors;
+            let format = self.format;
+            response.response.on_hover_ui_at_pointer(|ui| {
+                let slice = &sector.slice;
+                ui.label(RichText::new(&sector.name).size(14.0).monospace().strong());
+                egui::Grid::new(format!("{}_tooltip", self.name))
+                    .num_columns(2)
+                    .spacing([12.0, 2.0])
+                    .show(ui, |ui| {
+                        ui.label(RichText::new("Value").size(12.0).monospace());
+                        ui.label(
+                            RichText::new(format!(
+                                "{} ({:.2}%)",
+                                format.money(slice.value, currency),
+                                sector.percent
+                            ))
+                            .size(12.0)
+                            .monospace(),
+                        );
+                        ui.end_row();
+
+                        ui.label(RichText::new("Shares").size(12.0).monospace());
+                        ui.label(
+                            RichText::new(slice.shares.to_string())
+                                .size(12.0)
+                                .monospace(),
+                        );
+                        ui.end_row();
+
+                        let color = colors.of(slice.pnl);
+                        ui.label(RichText::new("P&L").size(12.0).monospace());
+                        ui.label(
+                            RichText::new(format.signed_money(slice.pnl, currency))
+                                .size(12.0)
+                                .color(color)
+                                .monospace(),
+                        );
+                        ui.end_row();
+                    });
+                if !sector.members.is_empty() {
+                    ui.label(
+                        RichText::new(sector.members.join(", "))
+                            .size(12.0)
+                            .monospace(),
+                    );
+                    ui.label(RichText::new("Click to expand").size(11.0).italics());
+                }
+            });
+        }
+
+        if !clicked {
+            return false;
+        }
+        if hovered.is_some_and(|index| !self.sectors[index].members.is_empty()) {
+            self.expanded = true;
+            self.layout();
+            return false;
+        }
+        // clicking the selected sector again, or outside the pie, clears the selection
+        let label = hovered.map(|index| self.sectors[index].name.clone());
+        let selected = if label == self.selected { None } else { label };
+        let changed = selected != self.selected;
+        self.selected = selected;
+        changed
     }
 }
 
@@ -112,6 +334,10 @@ struct Sector {
     points: Vec<[f64; 2]>,
     center: [f64; 2],
     percent: f64,
+    slice: Slice,
+    color: Color32,
+    /// Holdings folded into this sector when it is "Other".
+    members: Vec<String>,
 }
 
 impl Default for Sector {
@@ -149,10 +375,12 @@ impl Sector {
             points,
             center,
             percent,
+            slice: Slice::default(),
+            color: COLOR_PRESET[0],
+            members: vec![],
         }
     }
 
-    #[allow(unused)]
     pub fn contains(&self, &PlotPoint { x, y }: &PlotPoint) -> bool {
         let r = y.hypot(x);
         let mut theta = x.atan2(y);
@@ -164,3 +392,70 @@ impl Sector {
         r < RADIUS && theta > self.start && theta < self.end
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chart(values: &[f64]) -> PieChart {
+        let slices: Vec<_> = values
+            .iter()
+            .enumerate()
+            .map(|(i, value)| Slice {
+                label: format!("S{}", i),
+                value: *value,
+                ..Default::default()
+            })
+            .collect();
+        PieChart::new(
+            "test",
+            &slices,
+            Currency::Usd,
+            ColorScheme::RedUp,
+            NumberFormat::default(),
+        )
+    }
+
+    fn names(chart: &PieChart) -> Vec<&str> {
+        chart.sectors.iter().map(|s| s.name.as_str()).collect()
+    }
+
+    #[test]
+    fn more_holdings_than_preset_colors() {
+        let chart = chart(&[1.0; 9]);
+        assert_eq!(chart.sectors.len(), 9);
+        assert_ne!(chart.sectors[6].color, chart.sectors[7].color);
+        assert_eq!(palette(8, ColorScheme::RedUp), chart.sectors[8].color);
+    }
+
+    #[test]
+    fn small_holdings_fold_into_other() {
+        let mut chart = chart(&[50.0, 30.0, 10.0, 4.0, 3.0, 3.0]);
+        assert_eq!(chart.sectors.len(), 6);
+
+        chart.group_other = true;
+        chart.layout();
+        assert_eq!(names(&chart), ["S0", "S1", "S2", OTHER]);
+        assert_eq!(chart.sectors[3].members, ["S3", "S4", "S5"]);
+        assert_eq!(chart.sectors[3].slice.value, 10.0);
+
+        chart.threshold = 3.5;
+        chart.layout();
+        assert_eq!(names(&chart), ["S0", "S1", "S2", "S3", OTHER]);
+        assert_eq!(chart.sectors[4].members, ["S4", "S5"]);
+
+        chart.expanded = true;
+        chart.layout();
+        assert_eq!(chart.sectors.len(), 6);
+        assert!(chart.sectors.iter().all(|s| s.members.is_empty()));
+    }
+
+    #[test]
+    fn a_single_small_holding_stays_on_its_own() {
+        let mut chart = chart(&[60.0, 37.0, 3.0]);
+        chart.group_other = true;
+        chart.layout();
+        assert_eq!(names(&chart), ["S0", "S1", "S2"]);
+        assert_eq!(chart.sectors[2].color, palette(2, ColorScheme::RedUp));
+    }
+}
@@ -0,0 +1,88 @@
+use crate::modals::calendar::parse_records;
+use crate::modals::data_reader::Record;
+use crate::modals::performance::window;
+use chrono::NaiveDate;
+
+type Series = Vec<(NaiveDate, f64)>;
+
+/// Balance history and the money moved into it, narrowed to the range picked in the
+/// period chart. Shared by the panels that recompute whenever that range moves.
+#[derive(Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct RangedHistory {
+    data: Vec<Record>,
+    flows: Vec<Record>,
+    #[serde(skip)]
+    range: Option<(NaiveDate, NaiveDate)>,
+}
+
+impl RangedHistory {
+    pub fn new(data: Vec<Record>, flows: Vec<Record>) -> Self {
+        Self {
+            data,
+            flows,
+            range: None,
+        }
+    }
+
+    /// Returns whether the range differs from the last one, so the caller knows to recompute.
+    pub fn set_range(&mut self, range: Option<(NaiveDate, NaiveDate)>) -> bool {
+        let changed = range != self.range;
+        self.range = range;
+        changed
+    }
+
+    /// Balances in range, the whole history without one, and every flow.
+    pub fn parsed(&self) -> (Series, Series) {
+        let balances = parse_records(&self.data);
+        let balances = match self.range {
+            Some((from, to)) => window(&balances, from, to),
+            None => balances,
+        };
+        (balances, parse_records(&self.flows))
+    }
+}
+
+/// First and last date of dated values sorted oldest first.
+pub fn span(values: &[(NaiveDate, f64)]) -> Option<(NaiveDate, NaiveDate)> {
+    Some((values.first()?.0, values.last()?.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modals::test_util::date;
+
+    fn records(values: &[(&str, f64)]) -> Vec<Record> {
+        values
+            .iter()
+            .map(|(date, balance)| Record {
+                date: date.to_string(),
+                balance: *balance,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn range_starts_at_the_last_balance_before_it() {
+        let mut history = RangedHistory::new(
+            records(&[
+                ("2024-01-03", 120.0),
+                ("2024-01-01", 100.0),
+                ("2024-01-05", 130.0),
+            ]),
+            records(&[("2024-01-04", 10.0)]),
+        );
+        assert_eq!(history.parsed().0.len(), 3);
+
+        assert!(history.set_range(Some((date("2024-01-04"), date("2024-01-04")))));
+        assert!(!history.set_range(Some((date("2024-01-04"), date("2024-01-04")))));
+        let (balances, flows) = history.parsed();
+        assert_eq!(balances, vec![(date("2024-01-03"), 120.0)]);
+        assert_eq!(flows, vec![(date("2024-01-04"), 10.0)]);
+        assert_eq!(
+            span(&balances),
+            Some((date("2024-01-03"), date("2024-01-03")))
+        );
+    }
+}
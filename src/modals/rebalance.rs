@@ -0,0 +1,512 @@
+use crate::modals::colors::ColorScheme;
+use crate::modals::currency::Currency;
+use crate::modals::format::NumberFormat;
+use egui::RichText;
+
+/// Wanted share of the portfolio for one symbol, or for a group of symbols.
+#[derive(Debug, serde::Deserialize, serde::Serialize, Clone, Default)]
+#[serde(default)]
+pub struct Target {
+    pub name: String,
+    pub symbols: Vec<String>,
+    /// Percent of the holdings' value plus the cash to deploy.
+    pub weight: f64,
+}
+
+/// Targets and the cash to deploy, kept between sessions with `cash` in USD.
+#[derive(Debug, serde::Deserialize, serde::Serialize, Clone, Default)]
+#[serde(default)]
+pub struct RebalancePlan {
+    pub targets: Vec<Target>,
+    pub cash: f64,
+}
+
+/// What to do with one holding to move it to its target.
+#[derive(Debug, Clone)]
+pub struct Trade {
+    pub symbol: String,
+    pub price: f64,
+    pub owned: u32,
+    /// Shares to buy, negative to sell.
+    pub shares: i64,
+    pub current_weight: f64,
+    /// `None` for holdings no target covers, they are left alone.
+    pub target_weight: Option<f64>,
+}
+
+impl Trade {
+    pub fn drift(&self) -> Option<f64> {
+        self.target_weight
+            .map(|target| self.current_weight - target)
+    }
+
+    pub fn amount(&self) -> f64 {
+        self.shares as f64 * self.price
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Plan {
+    pub trades: Vec<Trade>,
+    pub cash_left: f64,
+}
+
+/// Whole-share trades moving `holdings` (symbol, owned, price) towards `targets`.
+///
+/// A group target is split over its symbols in proportion to their current value.
+/// Shares are rounded to the nearest whole one, then buys are trimmed until the sells and
+/// `cash` pay for them, and what cash is left buys the most underweight symbols it can.
+/// Both steps first move whole blocks of shares, so the work does not grow with the amounts.
+pub fn plan(holdings: &[(String, u32, f64)], targets: &[Target], cash: f64) -> Plan {
+    let values: Vec<f64> = holdings
+        .iter()
+        .map(|(_, owned, price)| *owned as f64 * price)
+        .collect();
+    let total = values.iter().sum::<f64>() + cash.max(0.0);
+    let weight = |value: f64| {
+        if total > 0.0 {
+            value / total * 100.0
+        } else {
+            0.0
+        }
+    };
+
+    let mut wanted: Vec<Option<f64>> = vec![None; holdings.len()];
+    for target in targets {
+        let members: Vec<usize> = holdings
+            .iter()
+            .enumerate()
+            .filter(|(_, (symbol, _, _))| target.symbols.iter().any(|s| s == symbol))
+            .map(|(i, _)| i)
+            .collect();
+        let group_value: f64 = members.iter().map(|&i| values[i]).sum();
+        for &i in &members {
+            let part = if group_value > 0.0 {
+                values[i] / group_value
+            } else {
+                1.0 / members.len() as f64
+            };
+            let value = target.weight / 100.0 * total * part;
+            wanted[i] = Some(wanted[i].unwrap_or(0.0) + value);
+        }
+    }
+
+    let mut shares: Vec<i64> = holdings
+        .iter()
+        .zip(&wanted)
+        .map(|((_, owned, price), wanted)| match wanted {
+            Some(value) if *price > 0.0 => (value / price).round() as i64 - *owned as i64,
+            _ => 0,
+        })
+        .collect();
+    let value_after =
+        |i: usize, shares: &[i64]| (holdings[i].1 as i64 + shares[i]) as f64 * holdings[i].2;
+    let spent = |shares: &[i64]| -> f64 {
+        shares
+            .iter()
+            .zip(holdings)
+            .map(|(n, (_, _, price))| *n as f64 * price)
+            .sum()
+    };
+    let mut cash_left = cash.max(0.0) - spent(&shares);
+    let max_price = holdings
+        .iter()
+        .map(|(_, _, price)| *price)
+        .fold(0.0, f64::max);
+
+    // cap how far any buy may end up over its target, lowering the cap until the money suffices
+    if cash_left < -1e-9 {
+        let planned = shares.clone();
+        let capped = |level: f64| -> Vec<i64> {
+            (0..holdings.len())
+                .map(|i| {
+                    let (_, owned, price) = &holdings[i];
+                    if planned[i] <= 0 || *price <= 0.0 {
+                        return planned[i];
+                    }
+                    let most =
+                        ((wanted[i].unwrap_or(0.0) + level) / price).floor() as i64 - *owned as i64;
+                    most.clamp(0, planned[i])
+                })
+                .collect()
+        };
+        // nothing is bought at the lowest level, nothing is capped at the highest
+        let (mut low, mut high) = (-(total + 1.0), total + max_price);
+        for _ in 0..100 {
+            let mid = (low + high) / 2.0;
+            if cash.max(0.0) - spent(&capped(mid)) >= -1e-9 {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+        shares = capped(low);
+        cash_left = cash.max(0.0) - spent(&shares);
+    }
+
+    // not enough money: drop buys from whatever ends up most over its target
+    while cash_left < -1e-9 {
+        let over = (0..holdings.len())
+            .filter(|&i| shares[i] > 0)
+            .max_by(|&a, &b| {
+                let excess = |i: usize| value_after(i, &shares) - wanted[i].unwrap_or(0.0);
+                excess(a).total_cmp(&excess(b))
+            });
+        let Some(i) = over else {
+            break;
+        };
+        shares[i] -= 1;
+        cash_left += holdings[i].2;
+    }
+
+    // money left: bring every holding within the same distance of its target, as close as
+    // the cash allows, counting only shares that each still bring it closer
+    let extra = |floor: f64, shares: &[i64]| -> Vec<i64> {
+        (0..holdings.len())
+            .map(|i| {
+                let price = holdings[i].2;
+                match wanted[i] {
+                    Some(wanted) if price > 0.0 => {
+                        let gap = wanted - value_after(i, shares) - floor.max(price / 2.0);
+                        ((gap / price).ceil() as i64).max(0)
+                    }
+                    _ => 0,
+                }
+            })
+            .collect()
+    };
+    let max_gap = (0..holdings.len())
+        .filter_map(|i| Some(wanted[i]? - value_after(i, &shares)))
+        .fold(0.0, f64::max);
+    let (mut low, mut high) = (0.0, max_gap);
+    if spent(&extra(low, &shares)) <= cash_left {
+        high = low;
+    }
+    for _ in 0..100 {
+        if high - low <= 0.0 {
+            break;
+        }
+        let mid = (low + high) / 2.0;
+        if spent(&extra(mid, &shares)) <= cash_left {
+            high = mid;
+        } else {
+            low = mid;
+        }
+    }
+    let bought = extra(high, &shares);
+    cash_left -= spent(&bought);
+    for (n, more) in shares.iter_mut().zip(bought) {
+        *n += more;
+    }
+
+    // the last shares one at a time, where each brings a holding closer to its target
+    loop {
+        let under = (0..holdings.len())
+            .filter(|&i| {
+                let price = holdings[i].2;
+                let Some(wanted) = wanted[i] else {
+                    return false;
+                };
+                price > 0.0 && price <= cash_left && wanted - value_after(i, &shares) > price / 2.0
+            })
+            .max_by(|&a, &b| {
+                let gap = |i: usize| wanted[i].unwrap_or(0.0) - value_after(i, &shares);
+                gap(a).total_cmp(&gap(b))
+            });
+        let Some(i) = under else {
+            break;
+        };
+        shares[i] += 1;
+        cash_left -= holdings[i].2;
+    }
+
+    let trades = holdings
+        .iter()
+        .enumerate()
+        .map(|(i, (symbol, owned, price))| Trade {
+            symbol: symbol.clone(),
+            price: *price,
+            owned: *owned,
+            shares: shares[i],
+            current_weight: weight(values[i]),
+            target_weight: wanted[i].map(weight),
+        })
+        .collect();
+    Plan { trades, cash_left }
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct RebalanceView {
+    name: String,
+    targets: Vec<Target>,
+    /// In USD whatever the currency shown, so switching currencies keeps its value.
+    cash: f64,
+    holdings: Vec<(String, u32, f64)>,
+    currency: Currency,
+    /// Multiplier from USD to `currency`.
+    factor: f64,
+    colors: ColorScheme,
+    format: NumberFormat,
+    #[serde(skip)]
+    symbols_text: Vec<String>,
+}
+
+impl Default for RebalanceView {
+    fn default() -> Self {
+        RebalanceView::new(
+            "default",
+            RebalancePlan::default(),
+            vec![],
+            Currency::Usd,
+            1.0,
+            ColorScheme::RedUp,
+            NumberFormat::default(),
+        )
+    }
+}
+
+impl RebalanceView {
+    /// `holdings` are (symbol, owned, close price) with prices in `currency`,
+    /// `factor` turns the USD cash of `saved` into `currency`.
+    pub fn new<S: AsRef<str>>(
+        name: S,
+        saved: RebalancePlan,
+        holdings: Vec<(String, u32, f64)>,
+        currency: Currency,
+        factor: f64,
+        colors: ColorScheme,
+        format: NumberFormat,
+    ) -> Self {
+        let symbols_text = saved.targets.iter().map(|t| t.symbols.join(", ")).collect();
+        Self {
+            name: name.as_ref().to_string(),
+            targets: saved.targets,
+            cash: saved.cash,
+            holdings,
+            currency,
+            factor,
+            colors,
+            format,
+            symbols_text,
+        }
+    }
+
+    pub fn saved(&self) -> RebalancePlan {
+        RebalancePlan {
+            targets: self.targets.clone(),
+            cash: self.cash,
+        }
+    }
+
+    /// One target per holding at its current weight, a starting point for editing.
+    fn targets_from_holdings(&mut self) {
+        let plan = plan(&self.holdings, &[], 0.0);
+        self.targets = plan
+            .trades
+            .iter()
+            .map(|trade| Target {
+                name: trade.symbol.clone(),
+                symbols: vec![trade.symbol.clone()],
+                weight: (trade.current_weight * 10.0).round() / 10.0,
+            })
+            .collect();
+        self.symbols_text = self.targets.iter().map(|t| t.symbols.join(", ")).collect();
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui) {
+        self.symbols_text.resize(self.targets.len(), String::new());
+        let mut removed = None;
+        egui::Grid::new(format!("{}_targets", self.name))
+            .num_columns(4)
+            .spacing([8.0, 4.0])
+            .show(ui, |ui| {
+                ui.label("Name");
+                ui.label("Symbols");
+                ui.label("Weight");
+                ui.end_row();
+                for (i, target) in self.targets.iter_mut().enumerate() {
+                    ui.add(egui::TextEdit::singleline(&mut target.name).desired_width(70.0));
+                    let symbols = ui.add(
+                        egui::TextEdit::singleline(&mut self.symbols_text[i])
+                            .hint_text("AAPL, MSFT")
+                            .desired_width(110.0),
+                    );
+                    if symbols.changed() {
+                        target.symbols = self.symbols_text[i]
+                            .split(',')
+                            .map(|s| s.trim().to_uppercase())
+                            .filter(|s| !s.is_empty())
+                            .collect();
+                    }
+                    ui.add(
+                        egui::DragValue::new(&mut target.weight)
+                            .clamp_range(0.0..=100.0)
+                            .speed(0.1)
+                            .suffix("%"),
+                    );
+                    if ui.small_button("Delete").clicked() {
+                        removed = Some(i);
+                    }
+                    ui.end_row();
+                }
+            });
+        if let Some(i) = removed {
+            self.targets.remove(i);
+            self.symbols_text.remove(i);
+        }
+
+        ui.horizontal(|ui| {
+            if ui.button("Add target").clicked() {
+                self.targets.push(Target::default());
+                self.symbols_text.push(String::new());
+            }
+            if ui.button("From holdings").clicked() {
+                self.targets_from_holdings();
+            }
+        });
+
+        let total: f64 = self.targets.iter().map(|t| t.weight).sum();
+        let total_text = RichText::new(format!("Targets add up to {:.1}%", total)).size(12.0);
+        ui.label(if total > 100.0 + 1e-9 {
+            total_text.color(ui.visuals().error_fg_color)
+        } else {
+            total_text
+        });
+        let (prefix, suffix) = self.format.affixes(self.currency);
+        let mut cash = self.cash * self.factor;
+        ui.horizontal(|ui| {
+            ui.label("Cash to deploy");
+            let edited = ui.add(
+                egui::DragValue::new(&mut cash)
+                    .clamp_range(0.0..=1e15)
+                    .speed(10.0)
+                    .prefix(prefix)
+                    .suffix(suffix),
+            );
+            if edited.changed() && self.factor > 0.0 {
+                self.cash = cash / self.factor;
+            }
+        });
+        ui.separator();
+
+        let plan = plan(&self.holdings, &self.targets, cash);
+        let text = |text: String| RichText::new(text).size(12.0).monospace();
+        egui::Grid::new(format!("{}_plan", self.name))
+            .num_columns(5)
+            .spacing([16.0, 2.0])
+            .show(ui, |ui| {
+                for header in ["Symbol", "Current", "Target", "Drift", "Trade"] {
+                    ui.label(text(header.to_string()));
+                }
+                ui.end_row();
+                for trade in &plan.trades {
+                    ui.label(text(trade.symbol.clone()));
+                    ui.label(text(format!("{:.1}%", trade.current_weight)));
+                    match (trade.target_weight, trade.drift()) {
+                        (Some(target), Some(drift)) => {
+                            ui.label(text(format!("{:.1}%", target)));
+                            ui.label(text(format!("{:+.1}%p", drift)));
+                        }
+                        _ => {
+                            ui.label(text("-".to_string()));
+                            ui.label(text("-".to_string()));
+                        }
+                    }
+                    let trade_text = match trade.shares {
+                        0 => text("Hold".to_string()),
+                        n => {
+                            let color = self.colors.of(n as f64);
+                            text(format!(
+                                "{} {} ({})",
+                                if n > 0 { "Buy" } else { "Sell" },
+                                n.abs(),
+                                self.format.money(trade.amount().abs(), self.currency)
+                            ))
+                            .color(color)
+                        }
+                    };
+                    ui.label(trade_text);
+                    ui.end_row();
+                }
+            });
+        ui.label(text(format!(
+            "Cash left {}",
+            self.format.money(plan.cash_left, self.currency)
+        )));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn holdings() -> Vec<(String, u32, f64)> {
+        vec![("A".to_string(), 10, 100.0), ("B".to_string(), 0, 30.0)]
+    }
+
+    fn target(symbols: &[&str], weight: f64) -> Target {
+        Target {
+            name: symbols.join("+"),
+            symbols: symbols.iter().map(|s| s.to_string()).collect(),
+            weight,
+        }
+    }
+
+    #[test]
+    fn sells_to_fund_buys_in_whole_shares() {
+        let plan = plan(
+            &holdings(),
+            &[target(&["A"], 50.0), target(&["B"], 50.0)],
+            0.0,
+        );
+        assert_eq!(plan.trades[0].shares, -5);
+        // 500 from the sale buys 16 shares at 30, 17 would overdraw
+        assert_eq!(plan.trades[1].shares, 16);
+        assert!((plan.cash_left - 20.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn cash_is_deployed_towards_targets() {
+        let plan = plan(
+            &holdings(),
+            &[target(&["A"], 50.0), target(&["B"], 50.0)],
+            1000.0,
+        );
+        assert_eq!(plan.trades[0].shares, 0);
+        assert_eq!(plan.trades[1].shares, 33);
+        assert!(plan.cash_left >= 0.0);
+    }
+
+    #[test]
+    fn untargeted_holdings_are_held() {
+        let plan = plan(&holdings(), &[target(&["B"], 10.0)], 0.0);
+        assert_eq!(plan.trades[0].shares, 0);
+        assert_eq!(plan.trades[0].target_weight, None);
+        // nothing to sell and no cash, so nothing can be bought
+        assert_eq!(plan.trades[1].shares, 0);
+    }
+
+    #[test]
+    fn group_targets_split_by_current_value() {
+        let holdings = vec![("A".to_string(), 10, 10.0), ("B".to_string(), 30, 10.0)];
+        let plan = plan(&holdings, &[target(&["A", "B"], 100.0)], 400.0);
+        assert_eq!(plan.trades[0].shares, 10);
+        assert_eq!(plan.trades[1].shares, 30);
+    }
+
+    #[test]
+    fn large_amounts_are_planned_in_bulk() {
+        let holdings = vec![("A".to_string(), 0, 0.01), ("B".to_string(), 0, 0.03)];
+        // over 100% in total, so the buys have to be trimmed as well
+        let targets = [target(&["A"], 80.0), target(&["B"], 80.0)];
+        let plan = plan(&holdings, &targets, 1e9);
+        assert!(plan.cash_left >= 0.0 && plan.cash_left < 0.03);
+        let spent: f64 = plan.trades.iter().map(|t| t.amount()).sum();
+        assert!((spent + plan.cash_left - 1e9).abs() < 1.0);
+        // the shortfall is shared, both end up the same distance short of their targets
+        let a = plan.trades[0].amount() - 8e8;
+        let b = plan.trades[1].amount() - 8e8;
+        assert!((a - b).abs() < 0.1);
+    }
+}
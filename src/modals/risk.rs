@@ -0,0 +1,432 @@
+use crate::modals::colors::ColorScheme;
+use crate::modals::data_reader::Record;
+use crate::modals::performance::{step_returns, window};
+use crate::modals::ranged_history::{span, RangedHistory};
+use chrono::NaiveDate;
+use egui::RichText;
+
+/// Deepest fall of a value series from a previous high.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Drawdown {
+    pub peak: NaiveDate,
+    pub trough: NaiveDate,
+    /// First date back at the peak value, if it ever got there.
+    pub recovery: Option<NaiveDate>,
+    /// Fall from the peak as a negative fraction, -0.25 for a quarter lost.
+    pub depth: f64,
+}
+
+/// Risk statistics of the balance history between two dates.
+#[derive(Debug, Clone, Copy)]
+pub struct RiskMetrics {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub cagr: Option<f64>,
+    pub volatility: Option<f64>,
+    pub drawdown: Option<Drawdown>,
+    pub sharpe: Option<f64>,
+    pub sortino: Option<f64>,
+    pub calmar: Option<f64>,
+}
+
+impl RiskMetrics {
+    /// `risk_free` is a yearly rate, 0.03 for 3%.
+    pub fn compute(
+        balances: &[(NaiveDate, f64)],
+        flows: &[(NaiveDate, f64)],
+        from: NaiveDate,
+        to: NaiveDate,
+        risk_free: f64,
+    ) -> Option<RiskMetrics> {
+        let balances = window(balances, from, to);
+        let first = balances.first()?.0;
+        let last = balances.last()?.0;
+        let returns: Vec<f64> = step_returns(&balances, flows)
+            .into_iter()
+            .map(|(_, r)| r)
+            .collect();
+        let periods = periods_per_year(&balances)?;
+        let index = wealth_index(&balances, flows);
+        let cagr = cagr(&index);
+        let drawdown = max_drawdown(&index);
+        Some(RiskMetrics {
+            from: first,
+            to: last,
+            cagr,
+            volatility: annualized_volatility(&returns, periods),
+            drawdown,
+            sharpe: sharpe(&returns, risk_free, periods),
+            sortino: sortino(&returns, risk_free, periods),
+            calmar: cagr.zip(drawdown).and_then(|(c, d)| calmar(c, d)),
+        })
+    }
+}
+
+pub(crate) fn mean(values: &[f64]) -> Option<f64> {
+    (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+}
+
+/// Sample standard deviation.
+fn std_dev(values: &[f64]) -> Option<f64> {
+    if values.len() < 2 {
+        return None;
+    }
+    let mean = mean(values)?;
+    let variance =
+        values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (values.len() - 1) as f64;
+    Some(variance.sqrt())
+}
+
+/// How many balances a year the series has, history skips weekends and holidays.
+pub fn periods_per_year(balances: &[(NaiveDate, f64)]) -> Option<f64> {
+    let (first, last) = (balances.first()?.0, balances.last()?.0);
+    let years = (last - first).num_days() as f64 / 365.0;
+    (years > 0.0 && balances.len() > 1).then(|| (balances.len() - 1) as f64 / years)
+}
+
+/// Growth of one unit invested at the first balance, with cash flows taken out.
+pub fn wealth_index(
+    balances: &[(NaiveDate, f64)],
+    flows: &[(NaiveDate, f64)],
+) -> Vec<(NaiveDate, f64)> {
+    let Some((first, _)) = balances.first() else {
+        return vec![];
+    };
+    let mut index = vec![(*first, 1.0)];
+    for (date, r) in step_returns(balances, flows) {
+        let last = index.last().map(|(_, value)| *value).unwrap_or(1.0);
+        index.push((date, last * (1.0 + r)));
+    }
+    index
+}
+
+/// Compound yearly growth of a wealth index.
+pub fn cagr(index: &[(NaiveDate, f64)]) -> Option<f64> {
+    let (first, last) = (index.first()?, index.last()?);
+    let years = (last.0 - first.0).num_days() as f64 / 365.0;
+    (years > 0.0 && first.1 > 0.0).then(|| (last.1 / first.1).powf(1.0 / years) - 1.0)
+}
+
+/// Standard deviation of the step returns scaled to a year.
+pub fn annualized_volatility(returns: &[f64], periods_per_year: f64) -> Option<f64> {
+    std_dev(returns).map(|sd| sd * periods_per_year.sqrt())
+}
+
+/// Largest fall from a running high, `None` when the series never falls.
+pub fn max_drawdown(index: &[(NaiveDate, f64)]) -> Option<Drawdown> {
+    let mut peak = *index.first()?;
+    let mut deepest: Option<Drawdown> = None;
+    let mut deepest_peak = 0.0;
+    for &(date, value) in index {
+        if value > peak.1 {
+            peak = (date, value);
+        }
+        let depth = value / peak.1 - 1.0;
+        if depth < deepest.map(|d| d.depth).unwrap_or(0.0) {
+            deepest = Some(Drawdown {
+                peak: peak.0,
+                trough: date,
+                recovery: None,
+                depth,
+            });
+            deepest_peak = peak.1;
+        }
+    }
+    deepest.map(|drawdown| Drawdown {
+        recovery: index
+            .iter()
+            .find(|(date, value)| *date > drawdown.trough && *value >= deepest_peak)
+            .map(|(date, _)| *date),
+        ..drawdown
+    })
+}
+
+/// Fall from the running high at every point, as a negative fraction.
+pub fn underwater(index: &[(NaiveDate, f64)]) -> Vec<(NaiveDate, f64)> {
+    let mut peak = f64::MIN;
+    index
+        .iter()
+        .map(|&(date, value)| {
+            peak = peak.max(value);
+            (date, if peak > 0.0 { value / peak - 1.0 } else { 0.0 })
+        })
+        .collect()
+}
+
+/// Excess return per unit of volatility, yearly.
+pub fn sharpe(returns: &[f64], risk_free: f64, periods_per_year: f64) -> Option<f64> {
+    let excess = mean(returns)? - risk_free / periods_per_year;
+    let sd = std_dev(returns)?;
+    (sd > 0.0).then(|| excess / sd * periods_per_year.sqrt())
+}
+
+/// Like Sharpe, but only the steps below the risk-free rate count as risk.
+pub fn sortino(returns: &[f64], risk_free: f64, periods_per_year: f64) -> Option<f64> {
+    let target = risk_free / periods_per_year;
+    let excess = mean(returns)? - target;
+    let downside: Vec<f64> = returns
+        .iter()
+        .map(|r| (r - target).min(0.0).powi(2))
+        .collect();
+    let deviation = mean(&downside)?.sqrt();
+    (deviation > 0.0).then(|| excess / deviation * periods_per_year.sqrt())
+}
+
+/// Yearly growth per unit of the deepest drawdown.
+pub fn calmar(cagr: f64, drawdown: Drawdown) -> Option<f64> {
+    (drawdown.depth < 0.0).then(|| cagr / -drawdown.depth)
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct RiskPanel {
+    name: String,
+    history: RangedHistory,
+    risk_free: f64,
+    colors: ColorScheme,
+    #[serde(skip)]
+    metrics: Option<RiskMetrics>,
+}
+
+impl Default for RiskPanel {
+    fn default() -> Self {
+        RiskPanel::new("default", vec![], vec![], 0.0, ColorScheme::RedUp)
+    }
+}
+
+impl RiskPanel {
+    /// `risk_free` is the yearly rate in percent.
+    pub fn new<S: AsRef<str>>(
+        name: S,
+        data: Vec<Record>,
+        flows: Vec<Record>,
+        risk_free: f64,
+        colors: ColorScheme,
+    ) -> Self {
+        let mut panel = Self {
+            name: name.as_ref().to_string(),
+            history: RangedHistory::new(data, flows),
+            risk_free,
+            colors,
+            metrics: None,
+        };
+        panel.compute();
+        panel
+    }
+
+    pub fn set_range(&mut self, range: Option<(NaiveDate, NaiveDate)>) {
+        if self.history.set_range(range) {
+            self.compute();
+        }
+    }
+
+    fn compute(&mut self) {
+        let (balances, flows) = self.history.parsed();
+        self.metrics = span(&balances).and_then(|(from, to)| {
+            RiskMetrics::compute(&balances, &flows, from, to, self.risk_free / 100.0)
+        });
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui) {
+        let Some(metrics) = self.metrics else {
+            ui.label(RichText::new("Not enough history").size(12.0).monospace());
+            return;
+        };
+        let text = |text: String| RichText::new(text).size(12.0).monospace();
+        let percent = |value: Option<f64>| match value {
+            Some(value) => text(format!("{:.2}%", value * 100.0)),
+            None => text("-".to_string()),
+        };
+        let ratio = |value: Option<f64>| match value {
+            Some(value) => text(format!("{:.2}", value)),
+            None => text("-".to_string()),
+        };
+        let cagr_color = self.colors.of(metrics.cagr.unwrap_or(0.0));
+
+        egui::Grid::new(format!("{}_grid", self.name))
+            .num_columns(2)
+            .spacing([24.0, 2.0])
+            .show(ui, |ui| {
+                ui.label(text("Period".to_string()));
+                ui.label(text(format!("{} ~ {}", metrics.from, metrics.to)));
+                ui.end_row();
+
+                ui.label(text("CAGR".to_string()));
+                ui.label(percent(metrics.cagr).color(cagr_color));
+                ui.end_row();
+
+                ui.label(text("Volatility".to_string()))
+                    .on_hover_text("Standard deviation of the returns, annualized");
+                ui.label(percent(metrics.volatility));
+                ui.end_row();
+
+                ui.label(text("Max drawdown".to_string()));
+                ui.label(percent(metrics.drawdown.map(|d| d.depth)).color(self.colors.down()));
+                ui.end_row();
+
+                if let Some(drawdown) = metrics.drawdown {
+                    ui.label(text("Peak / trough".to_string()));
+                    ui.label(text(format!("{} / {}", drawdown.peak, drawdown.trough)));
+                    ui.end_row();
+                }
+
+                ui.label(text("Sharpe".to_string()))
+                    .on_hover_text(format!("Risk-free rate {:.2}%", self.risk_free));
+                ui.label(ratio(metrics.sharpe));
+                ui.end_row();
+
+                ui.label(text("Sortino".to_string()));
+                ui.label(ratio(metrics.sortino));
+                ui.end_row();
+
+                ui.label(text("Calmar".to_string()))
+                    .on_hover_text("CAGR over the max drawdown");
+                ui.label(ratio(metrics.calmar));
+                ui.end_row();
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modals::test_util::{date, roughly, series};
+    use std::f64::consts::FRAC_1_SQRT_2;
+
+    #[test]
+    fn mean_and_sample_std_dev() {
+        assert_eq!(mean(&[1.0, 2.0, 3.0]), Some(2.0));
+        assert_eq!(mean(&[]), None);
+        assert!(roughly(std_dev(&[1.0, 2.0, 3.0, 4.0]).unwrap(), 1.29099));
+        assert_eq!(std_dev(&[1.0]), None);
+    }
+
+    #[test]
+    fn periods_per_year_counts_steps() {
+        let balances = series(&[
+            ("2023-01-01", 1.0),
+            ("2023-07-02", 1.0),
+            ("2024-01-01", 1.0),
+        ]);
+        assert!(roughly(periods_per_year(&balances).unwrap(), 2.0));
+        assert_eq!(periods_per_year(&balances[..1]), None);
+    }
+
+    #[test]
+    fn wealth_index_removes_cash_flows() {
+        let balances = series(&[
+            ("2024-01-01", 100.0),
+            ("2024-01-02", 110.0),
+            ("2024-01-03", 220.0),
+        ]);
+        let flows = series(&[("2024-01-03", 100.0)]);
+        let index: Vec<f64> = wealth_index(&balances, &flows)
+            .into_iter()
+            .map(|(_, v)| v)
+            .collect();
+        assert_eq!(index.len(), 3);
+        assert!(roughly(index[1], 1.1));
+        assert!(roughly(index[2], 1.2));
+    }
+
+    #[test]
+    fn cagr_over_two_years() {
+        let index = series(&[("2022-01-01", 1.0), ("2024-01-01", 1.21)]);
+        assert!(roughly(cagr(&index).unwrap(), 0.1));
+        assert_eq!(cagr(&index[..1]), None);
+    }
+
+    #[test]
+    fn volatility_is_annualized() {
+        let volatility = annualized_volatility(&[0.01, -0.01], 252.0).unwrap();
+        assert!(roughly(volatility, 0.0141421 * 252f64.sqrt()));
+    }
+
+    #[test]
+    fn max_drawdown_finds_peak_trough_and_recovery() {
+        let index = series(&[
+            ("2024-01-01", 100.0),
+            ("2024-01-02", 120.0),
+            ("2024-01-03", 90.0),
+            ("2024-01-04", 110.0),
+            ("2024-01-05", 125.0),
+        ]);
+        let drawdown = max_drawdown(&index).unwrap();
+        assert_eq!(drawdown.peak, date("2024-01-02"));
+        assert_eq!(drawdown.trough, date("2024-01-03"));
+        assert_eq!(drawdown.recovery, Some(date("2024-01-05")));
+        assert!(roughly(drawdown.depth, -0.25));
+
+        assert_eq!(max_drawdown(&index[..4]).unwrap().recovery, None);
+        assert_eq!(max_drawdown(&index[..2]), None);
+    }
+
+    #[test]
+    fn underwater_tracks_the_running_high() {
+        let index = series(&[
+            ("2024-01-01", 100.0),
+            ("2024-01-02", 80.0),
+            ("2024-01-03", 120.0),
+            ("2024-01-04", 90.0),
+        ]);
+        let depths: Vec<f64> = underwater(&index).into_iter().map(|(_, v)| v).collect();
+        assert!(roughly(depths[0], 0.0));
+        assert!(roughly(depths[1], -0.2));
+        assert!(roughly(depths[2], 0.0));
+        assert!(roughly(depths[3], -0.25));
+    }
+
+    #[test]
+    fn sharpe_subtracts_risk_free_rate() {
+        assert!(roughly(sharpe(&[0.02, 0.04], 0.0, 1.0).unwrap(), 2.12132));
+        assert!(roughly(
+            sharpe(&[0.02, 0.04], 0.02, 1.0).unwrap(),
+            FRAC_1_SQRT_2
+        ));
+        assert_eq!(sharpe(&[0.02, 0.02], 0.0, 1.0), None);
+    }
+
+    #[test]
+    fn sortino_only_counts_downside() {
+        assert!(roughly(sortino(&[0.05, -0.01], 0.0, 1.0).unwrap(), 2.82843));
+        assert_eq!(sortino(&[0.05, 0.01], 0.0, 1.0), None);
+    }
+
+    #[test]
+    fn calmar_divides_by_drawdown_depth() {
+        let drawdown = Drawdown {
+            peak: date("2024-01-01"),
+            trough: date("2024-01-02"),
+            recovery: None,
+            depth: -0.25,
+        };
+        assert!(roughly(calmar(0.1, drawdown).unwrap(), 0.4));
+        assert_eq!(
+            calmar(
+                0.1,
+                Drawdown {
+                    depth: 0.0,
+                    ..drawdown
+                }
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn compute_limits_to_the_range() {
+        let balances = series(&[
+            ("2023-01-01", 100.0),
+            ("2023-06-01", 90.0),
+            ("2024-01-01", 120.0),
+            ("2024-06-01", 110.0),
+        ]);
+        let metrics =
+            RiskMetrics::compute(&balances, &[], date("2023-03-01"), date("2024-01-01"), 0.0)
+                .unwrap();
+        assert_eq!(metrics.from, date("2023-01-01"));
+        assert_eq!(metrics.to, date("2024-01-01"));
+        assert!(roughly(metrics.drawdown.unwrap().depth, -0.1));
+        assert!(metrics.sharpe.is_some());
+    }
+}
@@ -0,0 +1,110 @@
+use crate::modals::colors::ColorScheme;
+use crate::modals::currency::Currency;
+use crate::modals::format::NumberFormat;
+use egui::RichText;
+
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Summary {
+    total_cost: f64,
+    market_value: f64,
+    realized: f64,
+    currency: Currency,
+    colors: ColorScheme,
+    format: NumberFormat,
+}
+
+impl Default for Summary {
+    fn default() -> Self {
+        Summary::new(
+            0.0,
+            0.0,
+            0.0,
+            Currency::Usd,
+            ColorScheme::RedUp,
+            NumberFormat::default(),
+        )
+    }
+}
+
+impl Summary {
+    pub fn new(
+        total_cost: f64,
+        market_value: f64,
+        realized: f64,
+        currency: Currency,
+        colors: ColorScheme,
+        format: NumberFormat,
+    ) -> Self {
+        Self {
+            total_cost,
+            market_value,
+            realized,
+            currency,
+            colors,
+            format,
+        }
+    }
+
+    pub fn pnl(&self) -> f64 {
+        self.market_value - self.total_cost
+    }
+
+    pub fn return_percent(&self) -> f64 {
+        if self.total_cost == 0.0 {
+            0.0
+        } else {
+            self.pnl() / self.total_cost * 100.0
+        }
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui) {
+        let color = self.colors.of(self.pnl());
+        egui::Grid::new("summary")
+            .num_columns(2)
+            .spacing([24.0, 2.0])
+            .show(ui, |ui| {
+                ui.label(RichText::new("Market value").size(12.0).monospace());
+                ui.label(
+                    RichText::new(self.format.money(self.market_value, self.currency))
+                        .size(16.0)
+                        .monospace(),
+                );
+                ui.end_row();
+
+                ui.label(RichText::new("Total cost").size(12.0).monospace());
+                ui.label(
+                    RichText::new(self.format.money(self.total_cost, self.currency))
+                        .size(12.0)
+                        .monospace(),
+                );
+                ui.end_row();
+
+                ui.label(RichText::new("Return").size(12.0).monospace());
+                ui.label(
+                    RichText::new(format!(
+                        "{} ({:+.2}%)",
+                        self.format.signed_money(self.pnl(), self.currency),
+                        self.return_percent()
+                    ))
+                    .size(12.0)
+                    .color(color)
+                    .monospace(),
+                );
+                ui.end_row();
+
+                if self.realized != 0.0 {
+                    let color = self.colors.of(self.realized);
+                    ui.label(RichText::new("Realized").size(12.0).monospace());
+                    ui.label(
+                        RichText::new(self.format.signed_money(self.realized, self.currency))
+                            .size(12.0)
+                            .color(color)
+                            .monospace(),
+                    );
+                    ui.end_row();
+                }
+            });
+        ui.separator();
+    }
+}
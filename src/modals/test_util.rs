@@ -0,0 +1,20 @@
+//! Fixtures shared by the unit tests of the modals.
+
+use chrono::NaiveDate;
+
+pub fn date(date: &str) -> NaiveDate {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap()
+}
+
+pub fn series(values: &[(&str, f64)]) -> Vec<(NaiveDate, f64)> {
+    values.iter().map(|(d, v)| (date(d), *v)).collect()
+}
+
+pub fn close(a: f64, b: f64) -> bool {
+    (a - b).abs() < 1e-6
+}
+
+/// For expected values written down to four or five decimals.
+pub fn roughly(a: f64, b: f64) -> bool {
+    (a - b).abs() < 1e-4
+}
@@ -0,0 +1,69 @@
+use crate::modals::{ColorScheme, CostBasis, Currency, Dimension, Locale, NumberFormat};
+
+/// User preferences, persisted separately from the app state.
+#[derive(serde::Deserialize, serde::Serialize, Clone)]
+#[serde(default)]
+pub struct Settings {
+    pub cost_basis: CostBasis,
+    pub currency: Currency,
+    /// Yearly rate in percent used by the risk ratios.
+    pub risk_free_rate: f64,
+    /// What the allocation pie groups the holdings by.
+    pub allocation: Dimension,
+    /// Colours for gains and losses in every widget.
+    pub colors: ColorScheme,
+    pub format: NumberFormat,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            cost_basis: CostBasis::Average,
+            currency: Currency::Usd,
+            risk_free_rate: 3.0,
+            allocation: Dimension::Symbol,
+            colors: ColorScheme::RedUp,
+            format: NumberFormat::default(),
+        }
+    }
+}
+
+impl Settings {
+    /// Returns true when a setting changed and the views need to be rebuilt.
+    pub fn show(&mut self, ui: &mut egui::Ui) -> bool {
+        let before = (self.cost_basis, self.colors, self.format);
+        ui.label("Cost basis");
+        for method in CostBasis::ALL {
+            ui.radio_value(&mut self.cost_basis, method, method.label());
+        }
+        ui.separator();
+        ui.label("Risk-free rate");
+        let rate = ui.add(
+            egui::DragValue::new(&mut self.risk_free_rate)
+                .clamp_range(0.0..=20.0)
+                .speed(0.05)
+                .suffix("%"),
+        );
+        ui.separator();
+        ui.label("Colors");
+        for colors in ColorScheme::ALL {
+            ui.radio_value(&mut self.colors, colors, colors.label());
+        }
+        ui.separator();
+        ui.label("Numbers");
+        for locale in Locale::ALL {
+            ui.radio_value(&mut self.format.locale, locale, locale.label());
+        }
+        ui.checkbox(&mut self.format.symbol_first, "Currency symbol first");
+        ui.horizontal(|ui| {
+            let mut fixed = self.format.decimals.is_some();
+            if ui.checkbox(&mut fixed, "Decimals").changed() {
+                self.format.decimals = fixed.then_some(2);
+            }
+            if let Some(decimals) = &mut self.format.decimals {
+                ui.add(egui::DragValue::new(decimals).clamp_range(0..=6));
+            }
+        });
+        (self.cost_basis, self.colors, self.format) != before || rate.changed()
+    }
+}
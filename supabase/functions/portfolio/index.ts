@@ -56,6 +56,22 @@ Deno.serve(async (_req) => {
         .update(updateJson)
         .eq('symbol',symbol);
 
+    const barDate = new Date(json['results'][0]['t']);
+    const { error: err_upsert } = await supabase.from('prices')
+        .upsert({
+          symbol : symbol,
+          date : barDate.toISOString().slice(0, 10),
+          open : json['results'][0]['o'],
+          high : json['results'][0]['h'],
+          low : json['results'][0]['l'],
+          close : json['results'][0]['c'],
+          volume : json['results'][0]['v'],
+        });
+
+    if (err_upsert) {
+      return ErrorResponse(err_upsert.message);
+    }
+
   }
 
 
@@ -89,7 +105,17 @@ Deno.serve(async (_req) => {
   }
 
 
-  return new Response(JSON.stringify({holdings,history}), {
+  let { data: prices, error: err_prices } = await supabase
+      .from('prices')
+      .select('symbol, date, open, high, low, close, volume')
+      .order('date');
+
+  if (err_prices) {
+    return ErrorResponse(err_prices.message);
+  }
+
+
+  return new Response(JSON.stringify({holdings,history,prices}), {
     headers: { 'Content-Type': 'application/json' },
     status: 200,
   })
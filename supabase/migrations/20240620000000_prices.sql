@@ -0,0 +1,22 @@
+-- 2024-06-20-add-prices.sql
+
+CREATE TABLE prices (
+    symbol VARCHAR(10) NOT NULL,
+    date DATE NOT NULL,
+    open NUMERIC(10,2) NOT NULL,
+    high NUMERIC(10,2) NOT NULL,
+    low NUMERIC(10,2) NOT NULL,
+    close NUMERIC(10,2) NOT NULL,
+    volume NUMERIC(16,0) NOT NULL DEFAULT 0,
+    PRIMARY KEY (symbol, date)
+);
+
+ALTER TABLE public.prices ENABLE ROW LEVEL SECURITY;
+
+
+CREATE POLICY anon_select_policy
+    ON public.prices
+    AS PERMISSIVE
+    FOR ALL
+    TO public
+    USING (auth.role() = 'anon');